@@ -0,0 +1,75 @@
+use std::time::Instant;
+use drones_3d::{Controls, Gamestate, MatchConfig, ObstacleConfig, SpawnPattern};
+
+// Time per game tick as the number of obstacles grows, then as the number of drones
+// grows with every drone shooting whenever it can. All obstacles arrive in a single wave
+// during the warm up, and no other wave comes before the timed ticks are over.
+
+// the first wave comes within a quarter of the wave interval
+const WARM_UP_TICKS: u32 = 100;
+const TIMED_TICKS: u32 = 200;
+
+const CONTROLS: Controls = Controls {
+    rot_y: 0.3,
+    pitch: 0.0,
+    roll: 0.0,
+    forward_back: 0.5,
+    up_down: 0.0,
+    strafe: 0.0,
+    shoot: true,
+    shot_angle: 0.0,
+    weapon: 0,
+};
+
+// microseconds per tick once the arena has filled up, and the final state
+fn time_ticks(team_size: usize, obstacles: u32) -> (f64, Gamestate) {
+    let config = MatchConfig {
+        max_game_ticks: WARM_UP_TICKS + TIMED_TICKS,
+        team_size,
+        obstacles: ObstacleConfig {
+            pattern: SpawnPattern::Waves { interval: WARM_UP_TICKS + TIMED_TICKS, count: obstacles },
+            min_radius: 0.5,
+            max_radius: 1.5,
+            ..ObstacleConfig::default()
+        },
+        ..MatchConfig::default()
+    };
+    let controls = vec![vec![CONTROLS; team_size]; 2];
+    let mut gamestate = Gamestate::new(config, 0);
+    for _ in 0..WARM_UP_TICKS {
+        gamestate.step(&controls);
+    }
+    let start = Instant::now();
+    for _ in 0..TIMED_TICKS {
+        gamestate.step(&controls);
+    }
+    (start.elapsed().as_secs_f64() * 1e6 / TIMED_TICKS as f64, gamestate)
+}
+
+fn main() {
+    println!("{:>10} {:>10} {:>12} {:>16}", "obstacles", "bullets", "us per tick", "ns per obstacle");
+    for count in [25, 50, 100, 200, 400, 800, 1600] {
+        let (per_tick, gamestate) = time_ticks(2, count);
+        let obstacles = gamestate.obstacles().count();
+        println!(
+            "{:>10} {:>10} {:>12.1} {:>16.1}",
+            obstacles,
+            gamestate.bullets().count(),
+            per_tick,
+            per_tick * 1e3 / obstacles.max(1) as f64
+        );
+    }
+    println!();
+    println!("{:>10} {:>10} {:>12} {:>16}", "drones", "bullets", "us per tick", "ns per drone");
+    for team_size in [1, 2, 5, 10, 25, 50, 100, 200] {
+        let (per_tick, gamestate) = time_ticks(team_size, 100);
+        let drones = gamestate.players().len();
+        println!(
+            "{:>10} {:>10} {:>12.1} {:>16.1}",
+            drones,
+            gamestate.bullets().count(),
+            per_tick,
+            per_tick * 1e3 / drones as f64
+        );
+    }
+}
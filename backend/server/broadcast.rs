@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use clap::ValueEnum;
+use serde::Serialize;
+use drones_3d::{FlatLayout, FlightModel, Gamestate, HealthConfig, Team, TeamObservation, WeaponsConfig};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ObservationFormat {
+    /// The whole gamestate as JSON, identical for every client (team-relative JSON
+    /// instead under fog of war, so that nothing hidden is revealed)
+    Full,
+    /// Team-relative JSON: my drones and score versus enemy drones and score
+    Team,
+    /// Team-relative flat f32 encoding, sent to competitors as binary frames
+    Flat,
+}
+
+// what competitors and environment clients are sent, spectators always get the full gamestate
+#[derive(Clone, Debug)]
+pub struct ObservationOptions {
+    pub format: ObservationFormat,
+    pub mirror: bool,
+    pub layout: FlatLayout,
+}
+
+// observations of every team at once, for environment clients
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum Observation<'a> {
+    Full(&'a Gamestate),
+    Team(HashMap<Team, TeamObservation>),
+    Flat(HashMap<Team, Vec<f32>>),
+}
+
+// first line sent to a competitor once it has identified itself, before the initial observation,
+// along with the rules that stay the same all match so that they aren't sent every tick
+#[derive(Serialize)]
+struct Handshake<'a> {
+    team: Team,
+    teams: usize,
+    team_size: usize,
+    // so that bots can predict how drones move
+    flight_model: &'a FlightModel,
+    // and how much damage they take
+    health: &'a HealthConfig,
+    weapons: &'a WeaponsConfig,
+}
+
+pub fn handshake(gamestate: &Gamestate, team: Team) -> Vec<u8> {
+    let handshake = Handshake {
+        team,
+        teams: gamestate.num_teams(),
+        team_size: gamestate.team_size(),
+        flight_model: &gamestate.config().flight_model,
+        health: &gamestate.config().health,
+        weapons: &gamestate.config().weapons,
+    };
+    format!("{}\n", serde_json::to_string(&handshake).unwrap()).into_bytes()
+}
+
+pub fn full_message(gamestate: &Gamestate) -> Vec<u8> {
+    format!("{}\n", serde_json::to_string(gamestate).unwrap()).into_bytes()
+}
+
+impl ObservationOptions {
+    fn format_for(&self, gamestate: &Gamestate) -> ObservationFormat {
+        if self.format == ObservationFormat::Full && gamestate.config().fog_of_war.is_some() {
+            ObservationFormat::Team
+        } else {
+            self.format
+        }
+    }
+    // JSON formats are newline terminated, flat frames are a little-endian u32 count of
+    // the f32 values that follow
+    pub fn competitor_message(&self, gamestate: &Gamestate, team: Team) -> Vec<u8> {
+        match self.format_for(gamestate) {
+            ObservationFormat::Full => full_message(gamestate),
+            ObservationFormat::Team => {
+                let observation = TeamObservation::new(gamestate, team, self.mirror);
+                format!("{}\n", serde_json::to_string(&observation).unwrap()).into_bytes()
+            },
+            ObservationFormat::Flat => {
+                let values = self.layout.encode(&TeamObservation::new(gamestate, team, self.mirror));
+                let mut frame = Vec::with_capacity(4 + 4 * values.len());
+                frame.extend_from_slice(&(values.len() as u32).to_le_bytes());
+                for value in values {
+                    frame.extend_from_slice(&value.to_le_bytes());
+                }
+                frame
+            },
+        }
+    }
+    pub fn all_teams<'a>(&self, gamestate: &'a Gamestate) -> Observation<'a> {
+        match self.format_for(gamestate) {
+            ObservationFormat::Full => Observation::Full(gamestate),
+            ObservationFormat::Team => Observation::Team(gamestate.teams()
+                .map(|team| (team, TeamObservation::new(gamestate, team, self.mirror)))
+                .collect()),
+            ObservationFormat::Flat => Observation::Flat(gamestate.teams()
+                .map(|team| (team, self.flat(gamestate, team)))
+                .collect()),
+        }
+    }
+    pub fn flat(&self, gamestate: &Gamestate, team: Team) -> Vec<f32> {
+        self.layout.encode(&TeamObservation::new(gamestate, team, self.mirror))
+    }
+}
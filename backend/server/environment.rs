@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use serde::Serialize;
+use drones_3d::{Controls, EndReason, FlightModel, Gamestate, HealthConfig, InputRaw, MatchConfig, MatchResult, RewardFunction, Team, WeaponsConfig};
+use drones_3d::observation::write_npy;
+use crate::broadcast::{Observation, ObservationOptions};
+
+// Reinforcement learning style interface for training mode. A single client drives
+// every team through RESET [seed] and STEP <inputs> commands, one JSON line per reply,
+// and can run any number of episodes without restarting the server. Episodes end
+// whenever the match would, as set by the match config.
+
+// each team's input, keyed by team name in any case, uses the same format a competitor
+// would send for its team. Teams left out stay idle
+type StepInput = HashMap<Team, InputRaw>;
+
+#[derive(Serialize)]
+struct Info {
+    seed: u64,
+    ticks_progressed: u32,
+    scores: HashMap<Team, i32>,
+    end_reason: Option<EndReason>,
+    // as decided by the game mode, so far
+    winner: Option<Team>,
+}
+
+#[derive(Serialize)]
+struct ResetResponse<'a> {
+    observation: Observation<'a>,
+    info: Info,
+    // rules that stay the same all episode, like in a competitor's handshake
+    flight_model: &'a FlightModel,
+    health: &'a HealthConfig,
+    weapons: &'a WeaponsConfig,
+}
+
+#[derive(Serialize)]
+struct StepResponse<'a> {
+    observation: Observation<'a>,
+    // from the reward function of the match config
+    reward: HashMap<Team, f32>,
+    drone_rewards: HashMap<Team, Vec<f32>>,
+    // truncated episodes ran out of time rather than reaching a terminal state
+    done: bool,
+    truncated: bool,
+    info: Info,
+    // every drone's match stats, once the episode is done
+    result: Option<MatchResult>,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+struct Episode {
+    gamestate: Gamestate,
+    seed: u64,
+}
+
+impl Episode {
+    fn new(seed: u64, config: &MatchConfig) -> Episode {
+        Episode {
+            gamestate: Gamestate::new(config.clone(), seed),
+            seed,
+        }
+    }
+    fn info(&self) -> Info {
+        Info {
+            seed: self.seed,
+            ticks_progressed: self.gamestate.ticks_progressed(),
+            scores: self.gamestate.teams()
+                .map(|team| (team, self.gamestate.score(team)))
+                .collect(),
+            end_reason: self.gamestate.end_reason(),
+            winner: self.gamestate.winner(),
+        }
+    }
+}
+
+// one environment client's connection
+struct Session<'a> {
+    config: &'a MatchConfig,
+    observations: &'a ObservationOptions,
+    episode: Option<Episode>,
+    // flat observations of every team for every observation sent, if they are being dumped
+    recording: Option<Vec<f32>>,
+}
+
+impl Session<'_> {
+    fn observe(&mut self) -> Observation<'_> {
+        let gamestate = &self.episode.as_ref().unwrap().gamestate;
+        if let Some(recording) = &mut self.recording {
+            for team in gamestate.teams() {
+                recording.extend(self.observations.flat(gamestate, team));
+            }
+        }
+        self.observations.all_teams(gamestate)
+    }
+    fn reset(&mut self, argument: &str) -> String {
+        let seed = if argument.is_empty() {
+            rand::random()
+        } else {
+            match argument.parse::<u64>() {
+                Ok(seed) => seed,
+                Err(_) => return error_response(format!("Invalid seed: {}", argument)),
+            }
+        };
+        let config = self.config;
+        let episode = Episode::new(seed, config);
+        let info = episode.info();
+        self.episode = Some(episode);
+        serde_json::to_string(&ResetResponse {
+            observation: self.observe(),
+            info,
+            flight_model: &config.flight_model,
+            health: &config.health,
+            weapons: &config.weapons,
+        }).unwrap()
+    }
+    fn step(&mut self, argument: &str) -> String {
+        let Some(episode) = &mut self.episode else {
+            return error_response(String::from("No episode running: RESET first"));
+        };
+        if episode.gamestate.is_over() {
+            return error_response(String::from("Episode is over: RESET first"));
+        }
+        let input: StepInput = match serde_json::from_str(argument) {
+            Ok(input) => input,
+            Err(e) => return error_response(format!("Invalid STEP input: {}", e)),
+        };
+        let controls: Vec<Vec<Controls>> = episode.gamestate.teams()
+            .map(|team| input.get(&team).map_or(vec![], |input| input.controls.clone()))
+            .collect();
+        episode.gamestate.step(&controls);
+        let rewards = self.config.reward.rewards(&episode.gamestate);
+        let teams: Vec<Team> = episode.gamestate.teams().collect();
+        let done = episode.gamestate.is_over();
+        let truncated = episode.gamestate.end_reason() == Some(EndReason::MaxGameTicks);
+        let info = episode.info();
+        let result = done.then(|| episode.gamestate.result());
+        serde_json::to_string(&StepResponse {
+            observation: self.observe(),
+            reward: teams.iter().map(|&team| (team, rewards.team(team))).collect(),
+            drone_rewards: teams.iter().map(|&team| (team, rewards.drones(team).to_vec())).collect(),
+            done,
+            truncated,
+            info,
+            result,
+        }).unwrap()
+    }
+    // shape is (observations sent, team, flat layout)
+    fn save_recording(&self, path: &Path) {
+        let Some(recording) = &self.recording else {
+            return;
+        };
+        let size = self.observations.layout.size();
+        let teams = self.config.teams.max(1);
+        let shape = [recording.len() / (teams * size), teams, size];
+        let result = File::create(path).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            write_npy(&mut writer, recording, &shape)?;
+            writer.flush()
+        });
+        match result {
+            Ok(()) => println!("Saved {} observations to {}", shape[0], path.display()),
+            Err(e) => eprintln!("Could not save observations to {}: {}", path.display(), e),
+        }
+    }
+}
+
+fn error_response(error: String) -> String {
+    serde_json::to_string(&ErrorResponse { error }).unwrap()
+}
+
+pub fn run(
+    stream: TcpStream,
+    config: &MatchConfig,
+    observations: &ObservationOptions,
+    observation_dump: Option<&Path>,
+) {
+    let mut reader = BufReader::new(stream.try_clone().expect("Could not clone the stream"));
+    let mut writer = stream;
+    let mut session = Session {
+        config,
+        observations,
+        episode: None,
+        recording: observation_dump.map(|_| vec![]),
+    };
+    if writer.write_all(b"READY\n").is_ok() {
+        loop {
+            let mut line = String::new();
+            match reader.read_line(&mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {},
+            }
+            let line = line.trim();
+            let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+            let response = match command {
+                "RESET" => session.reset(argument.trim()),
+                "STEP" => session.step(argument.trim()),
+                _ => error_response(format!("Unknown command: {}", command)),
+            };
+            if writer.write_all(format!("{}\n", response).as_bytes()).is_err() {
+                break;
+            }
+        }
+    }
+    println!("Environment client disconnected");
+    if let Some(path) = observation_dump {
+        session.save_recording(path);
+    }
+}
@@ -1,21 +1,26 @@
-use std::io::{Read, Write};
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
 use std::net::{TcpListener, TcpStream};
 use std::sync::{Arc, Mutex, Barrier};
+use std::path::PathBuf;
 use std::thread;
 use std::time::{Instant, Duration};
-use clap::Parser;
-use gamestate::Gamestate;
-use rand::thread_rng;
+use clap::{Parser, Subcommand};
+use broadcast::{ObservationFormat, ObservationOptions};
+use drones_3d::{FlatLayout, GameModeKind, Gamestate, MatchConfig, ReplayWriter, Team, TerminalEvent};
+use tournament::TournamentArgs;
 
-pub mod gamestate;
+mod broadcast;
+mod environment;
+mod ratings;
+mod tournament;
 
-// TCP buffer size for incoming client messages
-const BUFFER_SIZE: usize = 1024;
+// TCP buffer size for incoming client messages, enough for the controls of a large team
+const BUFFER_SIZE: usize = 16384;
 
 enum ConnectionType {
     Unknown,
-    CompetitorA,
-    CompetitorB,
+    Competitor(Team),
     Spectator
 }
 
@@ -34,34 +39,38 @@ fn read_until_newline(stream: &mut TcpStream, buf: &mut [u8;BUFFER_SIZE]) -> Str
 
     // Attempt to read up to delimiter
     if let Some(newline_index) = buf.iter().position(|&x| x == b'\n') {
-        return String::from_utf8_lossy(&buf[..newline_index]).to_string();
+        String::from_utf8_lossy(&buf[..newline_index]).to_string()
     } else {
         // client messages should have a known maximum size, and since clients/server
         // are synchronized, we shouldn't worry about clients sending multiple messages
         // before the server is ready to recieve them. If the buffer overflows, then
         // the client is at fault.
-        return String::from("ERR");
+        String::from("ERR")
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_client(
     mut stream: TcpStream,
-    num_competitors: Arc<Mutex<u32>>,
+    num_competitors: Arc<Mutex<usize>>,
     num_spectators: Arc<Mutex<u32>>,
     recieved_inputs: Arc<Barrier>,
     computed_next_tick: Arc<Barrier>,
     gamestate: Arc<Mutex<Gamestate>>,
-    input_a: Arc<Mutex<String>>,
-    input_b: Arc<Mutex<String>>,
+    // one per team, in team order
+    inputs: Arc<Mutex<Vec<String>>>,
     training_mode: bool,
     game_tick_delay: u64,
     competitor_max_debt: u128,
+    match_config: MatchConfig,
+    observations: ObservationOptions,
+    observation_dump: Option<PathBuf>,
+    replay: Arc<Mutex<Option<ReplayWriter<BufWriter<File>>>>>,
 ) {
     let mut connection_type: ConnectionType = ConnectionType::Unknown;
     let mut last_broadcast = Instant::now();
     let mut timeout_debt: u128 = 0;
     let mut buf: [u8;BUFFER_SIZE] = [0;BUFFER_SIZE];
-    let mut rng = thread_rng();
 
     loop {
 
@@ -72,20 +81,18 @@ fn handle_client(
                 match msg.trim() {
                     "COMPETITOR" => {
                         let mut num_competitors = num_competitors.lock().unwrap();
-                        // first to connect is CompetitorA, second is CompetitorB
-                        if *num_competitors == 0 {
-                            *num_competitors += 1;
-                            connection_type = ConnectionType::CompetitorA;
-                        } else if *num_competitors == 1 {
-                            *num_competitors += 1;
-                            connection_type = ConnectionType::CompetitorB;
-                        } else {
+                        let gamestate = gamestate.lock().unwrap();
+                        // teams are assigned in the order competitors connect
+                        if *num_competitors == gamestate.num_teams() {
                             println!("Reached max competitors already: disconnect");
                             return;
                         }
-                        let gamestate = gamestate.lock().unwrap();
+                        let team = Team(*num_competitors);
+                        *num_competitors += 1;
+                        connection_type = ConnectionType::Competitor(team);
                         stream
-                        .write(format!("{}\n", serde_json::to_string(&*gamestate).unwrap()).as_bytes())
+                            .write_all(&broadcast::handshake(&gamestate, team))
+                            .and_then(|_| stream.write_all(&observations.competitor_message(&gamestate, team)))
                             .expect("Could not write to the stream");
                         println!("Broadcasted initial gamestate");
                         last_broadcast = Instant::now();
@@ -105,20 +112,30 @@ fn handle_client(
                         }
                         let gamestate = gamestate.lock().unwrap();
                         stream
-                        .write(format!("{}\n", serde_json::to_string(&*gamestate).unwrap()).as_bytes())
+                            .write_all(&broadcast::full_message(&gamestate))
                             .expect("Could not write to the stream");
                         println!("Broadcasted initial gamestate");
                     },
+                    "ENVIRONMENT" => {
+                        if !training_mode {
+                            println!("Not in training mode, no environment clients allowed: disconnect");
+                            return;
+                        }
+                        // the environment client runs its own episodes, independent of competitors
+                        println!("Running environment episodes");
+                        environment::run(stream, &match_config, &observations, observation_dump.as_deref());
+                        return;
+                    },
                     _ => {
                         println!("Client failed to identify itself: disconnect");
                         return;
                     }
                 }
             },
-            ConnectionType::CompetitorA => {
+            ConnectionType::Competitor(team) => {
                 {
-                    let mut input_a = input_a.lock().unwrap();
-                    *input_a = read_until_newline(&mut stream, &mut buf);
+                    let mut inputs = inputs.lock().unwrap();
+                    inputs[team.0] = read_until_newline(&mut stream, &mut buf);
                 }
                 if !training_mode {
                     // measure time since last broadcast
@@ -139,64 +156,37 @@ fn handle_client(
                         }
                     }
                 }
-                // wait for the other competitor to send data
-                println!("Waiting for both inputs to be recieved");
+                // wait for the other competitors to send data
+                println!("Waiting for all inputs to be recieved");
                 recieved_inputs.wait();
-                println!("Recieved both inputs");
-                // compute gamestate in Competitor A's handler thread
-                // the distiction between thread A and B is arbitrary
-                {
-                    let input_b = input_b.lock().unwrap();
-                    let input_a = input_a.lock().unwrap();
+                println!("Recieved all inputs");
+                // compute gamestate in team A's handler thread
+                // the choice of thread is arbitrary
+                if team == Team::A {
+                    let inputs = inputs.lock().unwrap();
                     let mut gamestate = gamestate.lock().unwrap();
-                    gamestate.compute_next_tick(&mut rng, input_a.clone(), input_b.clone());
-                }
-                println!("Competitor A: synchronizing after gamestate computation");
-                computed_next_tick.wait();
-                println!("Synchronized");
-                // broadcast game state
-                stream
-                    .write(format!("{}\n", serde_json::to_string(&*gamestate).unwrap()).as_bytes())
-                    .expect("Could not write to the stream");
-                last_broadcast = Instant::now();
-            },
-            ConnectionType::CompetitorB => {
-                {
-                    let mut input_b = input_b.lock().unwrap();
-                    *input_b = read_until_newline(&mut stream, &mut buf);
-                }
-                // TODO: make the following lines into a function
-                if !training_mode {
-                    // measure time since last broadcast
-                    let since_last_broadcast = last_broadcast.elapsed();
-                    let remaining_sleep_time =
-                        Duration::from_millis(game_tick_delay) - since_last_broadcast;
-                    // Check if sleeping is needed
-                    if remaining_sleep_time > Duration::from_millis(0) {
-                        // Sleep until next game tick
-                        std::thread::sleep(remaining_sleep_time);
-                    }
-                    if remaining_sleep_time < Duration::from_millis(0) {
-                        // Add to timeout deficit
-                        timeout_debt -= remaining_sleep_time.as_millis();
-                        if timeout_debt > competitor_max_debt {
-                            println!("Competitor has taken too long: disconnect");
-                            return;
+                    gamestate.compute_next_tick(&inputs);
+                    let mut replay = replay.lock().unwrap();
+                    if let Some(writer) = replay.as_mut() {
+                        let recorded = writer.record(&gamestate).and_then(|_| {
+                            if gamestate.is_over() { writer.finish(&gamestate) } else { writer.flush() }
+                        });
+                        if let Err(e) = recorded {
+                            eprintln!("Could not record replay: {}", e);
+                        }
+                        // the replay ends with the match, even if the server plays on
+                        if gamestate.is_over() {
+                            *replay = None;
                         }
                     }
                 }
-                // wait for the other competitor to send data
-                println!("Waiting for both inputs to be recieved");
-                recieved_inputs.wait();
-                println!("Recieved both inputs");
-                // competitor A computes
-                println!("Competitor B: synchronizing after gamestate computation");
+                println!("Competitor {}: synchronizing after gamestate computation", team);
                 computed_next_tick.wait();
                 println!("Synchronized");
                 // broadcast game state
                 let gamestate = gamestate.lock().unwrap();
                 stream
-                .write(format!("{}\n", serde_json::to_string(&*gamestate).unwrap()).as_bytes())
+                    .write_all(&observations.competitor_message(&gamestate, team))
                     .expect("Could not write to the stream");
                 last_broadcast = Instant::now();
             },
@@ -207,7 +197,7 @@ fn handle_client(
                 // broadcast game state
                 let gamestate = gamestate.lock().unwrap();
                 stream
-                .write(format!("{}\n", serde_json::to_string(&*gamestate).unwrap()).as_bytes())
+                    .write_all(&broadcast::full_message(&gamestate))
                     .expect("Could not write to the stream");
                 last_broadcast = Instant::now();
             }
@@ -227,9 +217,65 @@ struct Args {
     /// Maximum total milliseconds a competitor can be "late" with their response
     #[arg(short, long, default_value_t = 1000)]
     competitor_max_debt: u128,
-    /// Maximum game ticks until the game is over
-    #[arg(short, long, default_value_t = 10000)]
-    max_game_ticks: u32
+    /// Match config file (JSON), unset fields keep their defaults
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Maximum game ticks until the game is over, overrides the match config
+    #[arg(short, long)]
+    max_game_ticks: Option<u32>,
+    /// Number of teams, each played by its own competitor, overrides the match config
+    #[arg(long)]
+    teams: Option<usize>,
+    /// Drones on each team, 1 for a free-for-all, overrides the match config
+    #[arg(long)]
+    team_size: Option<usize>,
+    /// Event that ends the game early, may be repeated, overrides the match config
+    #[arg(long, value_enum)]
+    terminal_event: Vec<TerminalEvent>,
+    /// Team score that ends the game early, overrides the match config
+    #[arg(long)]
+    score_limit: Option<i32>,
+    /// Game mode, with the match config's settings if it is that mode and default settings
+    /// otherwise, overrides the match config
+    #[arg(long, value_enum)]
+    mode: Option<GameModeKind>,
+    /// What competitors and environment clients are sent each tick
+    #[arg(long, value_enum, default_value_t = ObservationFormat::Full)]
+    observation_format: ObservationFormat,
+    /// Turn the team-relative observations of every team but A so all teams see the arena the same way
+    #[arg(long, default_value_t = false)]
+    mirror_observations: bool,
+    /// Number of nearest obstacles included in flat observations
+    #[arg(long, default_value_t = FlatLayout::default().nearest_obstacles)]
+    nearest_obstacles: usize,
+    /// Number of nearest bullets included in flat observations
+    #[arg(long, default_value_t = FlatLayout::default().nearest_bullets)]
+    nearest_bullets: usize,
+    /// Number of nearest missiles included in flat observations
+    #[arg(long, default_value_t = FlatLayout::default().nearest_missiles)]
+    nearest_missiles: usize,
+    /// Number of nearest mines included in flat observations
+    #[arg(long, default_value_t = FlatLayout::default().nearest_mines)]
+    nearest_mines: usize,
+    /// Number of nearest items included in flat observations
+    #[arg(long, default_value_t = FlatLayout::default().nearest_pickups)]
+    nearest_pickups: usize,
+    /// Training mode: save flat observations sent to environment clients as a .npy file
+    #[arg(long)]
+    observation_dump: Option<PathBuf>,
+    /// Record the match as a JSON lines replay: a header, then the gamestate and events of every tick
+    #[arg(long)]
+    replay: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Option<Command>
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run a headless tournament between bots and update their persisted ratings
+    Tournament(TournamentArgs),
+    /// Print the layout of flat observations as JSON
+    ObservationLayout
 }
 
 
@@ -238,19 +284,88 @@ fn main() {
     let training_mode = args.training_mode;
     let game_tick_delay = args.game_tick_delay;
     let competitor_max_debt = args.competitor_max_debt;
-    let max_game_ticks = args.max_game_ticks;
+    let mut match_config = match &args.config {
+        Some(path) => match MatchConfig::load(path) {
+            Ok(match_config) => match_config,
+            Err(e) => {
+                eprintln!("Could not load match config {}: {}", path.display(), e);
+                return;
+            }
+        },
+        None => MatchConfig::default(),
+    };
+    if let Some(max_game_ticks) = args.max_game_ticks {
+        match_config.max_game_ticks = max_game_ticks;
+    }
+    if let Some(teams) = args.teams {
+        match_config.teams = teams;
+    }
+    if let Some(team_size) = args.team_size {
+        match_config.team_size = team_size;
+    }
+    if !args.terminal_event.is_empty() {
+        match_config.terminal_events = args.terminal_event;
+    }
+    if args.score_limit.is_some() {
+        match_config.score_limit = args.score_limit;
+    }
+    if let Some(mode) = args.mode {
+        match_config.mode = match_config.mode.with_kind(mode);
+    }
+    let observations = ObservationOptions {
+        format: args.observation_format,
+        mirror: args.mirror_observations,
+        layout: FlatLayout {
+            teams: match_config.teams,
+            team_size: match_config.team_size,
+            nearest_obstacles: args.nearest_obstacles,
+            nearest_bullets: args.nearest_bullets,
+            nearest_missiles: args.nearest_missiles,
+            nearest_mines: args.nearest_mines,
+            nearest_pickups: args.nearest_pickups,
+        },
+    };
+    let observation_dump = args.observation_dump;
+    match args.command {
+        Some(Command::Tournament(tournament_args)) => {
+            tournament::run(tournament_args, &match_config, &observations);
+            return;
+        },
+        Some(Command::ObservationLayout) => {
+            let layout = serde_json::json!({
+                "size": observations.layout.size(),
+                "fields": observations.layout.fields(),
+            });
+            println!("{}", serde_json::to_string_pretty(&layout).unwrap());
+            return;
+        },
+        None => {},
+    }
     let listener = TcpListener::bind("127.0.0.1:44556").unwrap();
-    let num_competitors: Arc<Mutex<u32>> = Arc::new(Mutex::new(0));
+    let num_competitors: Arc<Mutex<usize>> = Arc::new(Mutex::new(0));
     let num_spectators = Arc::new(Mutex::new(0));
-    let gamestate = Arc::new(Mutex::new(Gamestate::new(&mut thread_rng(), max_game_ticks)));
-    let input_a = Arc::new(Mutex::new(String::new()));
-    let input_b = Arc::new(Mutex::new(String::new()));
-    // must recieve input from both threads before computing gamestate
-    let recieved_inputs = Arc::new(Barrier::new(2));
+    let gamestate = Gamestate::new(match_config.clone(), rand::random());
+    let replay = match &args.replay {
+        Some(path) => match ReplayWriter::create(path, &gamestate) {
+            Ok(replay) => Some(replay),
+            Err(e) => {
+                eprintln!("Could not record replay to {}: {}", path.display(), e);
+                return;
+            }
+        },
+        None => None,
+    };
+    let replay = Arc::new(Mutex::new(replay));
+    let teams = gamestate.num_teams();
+    let gamestate = Arc::new(Mutex::new(gamestate));
+    let inputs = Arc::new(Mutex::new(vec![String::new(); teams]));
+    // must recieve input from every competitor thread before computing gamestate
+    let recieved_inputs = Arc::new(Barrier::new(teams));
     // competitor and spectator handling threads must wait for new gamestate to be computed
     // don't sync with spectator thread in training mode
-    let computed_next_tick = Arc::new(Barrier::new(2 + if training_mode {0} else {1}));
-    let mut threads_spawned: u32 = 0;
+    let connections = teams + if training_mode {0} else {1};
+    let computed_next_tick = Arc::new(Barrier::new(connections));
+    let mut threads_spawned: usize = 0;
     for stream in listener.incoming() {
         match stream {
             Ok(stream) => {
@@ -260,22 +375,27 @@ fn main() {
                 let recieved_inputs = Arc::clone(&recieved_inputs);
                 let computed_next_tick = Arc::clone(&computed_next_tick);
                 let gamestate = Arc::clone(&gamestate);
-                let input_a = Arc::clone(&input_a);
-                let input_b = Arc::clone(&input_b);
-                // spawn 1 handler thread in training mode, spawn 2 otherwise
-                if threads_spawned < if training_mode {1} else {2} {
+                let inputs = Arc::clone(&inputs);
+                let match_config = match_config.clone();
+                let observations = observations.clone();
+                let observation_dump = observation_dump.clone();
+                let replay = Arc::clone(&replay);
+                // spawn a handler thread for every connection but the last
+                if threads_spawned + 1 < connections {
                     thread::spawn(move || {
                         handle_client(stream,num_competitors, has_spectator,
-                            recieved_inputs, computed_next_tick, gamestate, input_a, input_b,
-                            training_mode, game_tick_delay, competitor_max_debt
+                            recieved_inputs, computed_next_tick, gamestate, inputs,
+                            training_mode, game_tick_delay, competitor_max_debt, match_config,
+                            observations, observation_dump, replay
                         );
                     });
                     threads_spawned += 1;
                 } else {
                     // use the main thread to handle final connection to reduce thread usage
                     handle_client(stream, num_competitors, has_spectator,
-                        recieved_inputs, computed_next_tick, gamestate, input_a, input_b,
-                        training_mode, game_tick_delay, competitor_max_debt
+                        recieved_inputs, computed_next_tick, gamestate, inputs,
+                        training_mode, game_tick_delay, competitor_max_debt, match_config,
+                        observations, observation_dump, replay
                     );
                 }
             }
@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+use std::f64::consts::{LN_10, PI};
+use std::fs;
+use std::io;
+use std::path::Path;
+use clap::ValueEnum;
+use serde::{Serialize, Deserialize};
+
+const INITIAL_RATING: f64 = 1500.0;
+const ELO_K_FACTOR: f64 = 32.0;
+// Glicko rating deviation: new players start uncertain, and never become fully certain
+const INITIAL_DEVIATION: f64 = 350.0;
+const MIN_DEVIATION: f64 = 30.0;
+const GLICKO_Q: f64 = LN_10 / 400.0;
+// Glicko's growth of the deviation per rating period without games, about 100 periods
+// take a settled deviation of 50 back to the initial one
+const GLICKO_C: f64 = 34.6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum RatingSystem {
+    Elo,
+    Glicko,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Rating {
+    pub rating: f64,
+    pub deviation: f64, // only used by Glicko, kept so the systems can be swapped
+    pub games: u32,
+    #[serde(default)]
+    pub last_period: u64, // rating period of the player's last game
+}
+
+impl Default for Rating {
+    fn default() -> Rating {
+        Rating {
+            rating: INITIAL_RATING,
+            deviation: INITIAL_DEVIATION,
+            games: 0,
+            last_period: 0,
+        }
+    }
+}
+
+// Ratings of every bot that has ever played, persisted as JSON between tournaments
+#[derive(Default, Serialize, Deserialize)]
+pub struct Ratings {
+    players: BTreeMap<String, Rating>,
+    // rating periods so far, every game rated is one
+    #[serde(default)]
+    periods: u64,
+}
+
+// Glicko's attenuation of the opponent's rating by their deviation
+fn glicko_g(deviation: f64) -> f64 {
+    1.0 / (1.0 + 3.0 * GLICKO_Q.powi(2) * deviation.powi(2) / PI.powi(2)).sqrt()
+}
+
+// Glicko's deviation at the start of `period`, grown by the periods since the player's last game
+fn glicko_deviation(player: &Rating, period: u64) -> f64 {
+    let idle = period.saturating_sub(player.last_period) as f64;
+    (player.deviation.powi(2) + GLICKO_C.powi(2) * idle).sqrt().min(INITIAL_DEVIATION)
+}
+
+fn expected_score(rating: f64, opponent_rating: f64, g: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-g * (rating - opponent_rating) / 400.0))
+}
+
+impl Ratings {
+    // a missing file is a fresh ratings table
+    pub fn load(path: &Path) -> io::Result<Ratings> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Ratings::default()),
+            Err(e) => Err(e),
+        }
+    }
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self).unwrap())
+    }
+    pub fn get(&self, name: &str) -> Rating {
+        self.players.get(name).cloned().unwrap_or_default()
+    }
+    // outcome is from player a's perspective: 1 win, 0.5 draw, 0 loss
+    pub fn update(&mut self, system: RatingSystem, a: &str, b: &str, outcome: f64) {
+        self.periods += 1;
+        let mut rating_a = self.get(a);
+        let mut rating_b = self.get(b);
+        let (mut new_a, mut new_b) = match system {
+            RatingSystem::Elo => (
+                Ratings::update_elo(&rating_a, &rating_b, outcome),
+                Ratings::update_elo(&rating_b, &rating_a, 1.0 - outcome),
+            ),
+            RatingSystem::Glicko => {
+                rating_a.deviation = glicko_deviation(&rating_a, self.periods);
+                rating_b.deviation = glicko_deviation(&rating_b, self.periods);
+                (
+                    Ratings::update_glicko(&rating_a, &rating_b, outcome),
+                    Ratings::update_glicko(&rating_b, &rating_a, 1.0 - outcome),
+                )
+            },
+        };
+        new_a.last_period = self.periods;
+        new_b.last_period = self.periods;
+        self.players.insert(a.to_string(), new_a);
+        self.players.insert(b.to_string(), new_b);
+    }
+    fn update_elo(player: &Rating, opponent: &Rating, outcome: f64) -> Rating {
+        let expected = expected_score(player.rating, opponent.rating, 1.0);
+        Rating {
+            rating: player.rating + ELO_K_FACTOR * (outcome - expected),
+            deviation: player.deviation,
+            games: player.games + 1,
+            last_period: player.last_period,
+        }
+    }
+    // Glicko-1, treating every game as its own rating period
+    fn update_glicko(player: &Rating, opponent: &Rating, outcome: f64) -> Rating {
+        let g = glicko_g(opponent.deviation);
+        let expected = expected_score(player.rating, opponent.rating, g);
+        let d2 = 1.0 / (GLICKO_Q.powi(2) * g.powi(2) * expected * (1.0 - expected));
+        let precision = 1.0 / player.deviation.powi(2) + 1.0 / d2;
+        Rating {
+            rating: player.rating + GLICKO_Q / precision * g * (outcome - expected),
+            deviation: (1.0 / precision).sqrt().max(MIN_DEVIATION),
+            games: player.games + 1,
+            last_period: player.last_period,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glicko_deviation_grows_back_while_a_player_sits_out() {
+        let mut ratings = Ratings::default();
+        for _ in 0..20 {
+            ratings.update(RatingSystem::Glicko, "a", "b", 0.5);
+        }
+        let settled = ratings.get("a").deviation;
+        for _ in 0..20 {
+            ratings.update(RatingSystem::Glicko, "b", "c", 0.5);
+        }
+        // a's first game back starts from a wider deviation than it left with
+        let idle = glicko_deviation(&ratings.get("a"), ratings.periods + 1);
+        assert!(idle > settled);
+        assert!((idle.powi(2) - settled.powi(2) - GLICKO_C.powi(2) * 21.0).abs() < 1e-6);
+        // and never past where new players start
+        assert_eq!(glicko_deviation(&ratings.get("a"), 10_000), INITIAL_DEVIATION);
+    }
+}
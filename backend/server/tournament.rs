@@ -0,0 +1,566 @@
+use std::collections::HashSet;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use clap::{Args, ValueEnum};
+use drones_3d::{GameModeKind, Gamestate, MatchConfig, PlayerResult, ReplayWriter, Team};
+use crate::broadcast::{self, ObservationOptions};
+use crate::ratings::{RatingSystem, Ratings};
+
+// bots launched from a command find their match server through this variable
+pub const SERVER_ADDR_ENV: &str = "DRONES3D_SERVER_ADDR";
+const ADDRESS_PREFIX: &str = "tcp://";
+
+#[derive(Clone, Debug)]
+enum BotSource {
+    // program and arguments, launched once per game
+    Command(Vec<String>),
+    // a bot that is already running and listening for the match server
+    Address(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct BotEntry {
+    name: String,
+    source: BotSource,
+}
+
+impl BotEntry {
+    // `[name=]program args...` or `[name=]tcp://host:port`
+    fn parse(entry: &str) -> Result<BotEntry, String> {
+        let (name, spec) = match entry.split_once('=') {
+            Some((name, spec)) if !name.is_empty()
+                && !name.contains(|c: char| c.is_whitespace() || c == '/') => (name, spec),
+            _ => (entry, entry),
+        };
+        let source = match spec.strip_prefix(ADDRESS_PREFIX) {
+            Some(address) => BotSource::Address(address.to_string()),
+            None => BotSource::Command(spec.split_whitespace().map(String::from).collect()),
+        };
+        if let BotSource::Command(args) = &source {
+            if args.is_empty() {
+                return Err(format!("bot entry '{}' has no command", entry));
+            }
+        }
+        Ok(BotEntry { name: name.to_string(), source })
+    }
+}
+
+// the first name entered more than once, ratings and standings are kept by name
+fn duplicate_name(bots: &[BotEntry]) -> Option<&str> {
+    let mut names = HashSet::new();
+    bots.iter().map(|bot| bot.name.as_str()).find(|name| !names.insert(*name))
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum PairingFormat {
+    RoundRobin,
+    Swiss,
+}
+
+#[derive(Args, Debug)]
+pub struct TournamentArgs {
+    /// Bots to enter, each either `[name=]command args...` or `[name=]tcp://host:port`
+    #[arg(required = true, num_args = 2.., value_parser = BotEntry::parse)]
+    bots: Vec<BotEntry>,
+    /// How pairings are scheduled
+    #[arg(long, value_enum, default_value_t = PairingFormat::RoundRobin)]
+    format: PairingFormat,
+    /// Number of Swiss rounds, defaults to log2 of the number of bots
+    #[arg(long)]
+    rounds: Option<u32>,
+    /// Games played per pairing, bots swap sides between games
+    #[arg(long, default_value_t = 2)]
+    games_per_pairing: u32,
+    /// Rating system used to update the ratings file
+    #[arg(long, value_enum, default_value_t = RatingSystem::Elo)]
+    rating: RatingSystem,
+    /// File the ratings are loaded from and saved to
+    #[arg(long, default_value = "ratings.json")]
+    ratings_file: PathBuf,
+    /// Milliseconds a bot may take to connect or to respond before forfeiting the game
+    #[arg(long, default_value_t = 5000)]
+    response_timeout: u64,
+    /// Directory to record a replay of every game into
+    #[arg(long)]
+    replay_dir: Option<PathBuf>,
+    /// Game mode to play in turn with the others given, one per round, may be repeated.
+    /// Each has the match config's settings if it is that mode and default settings otherwise
+    #[arg(long, value_enum)]
+    rotate_mode: Vec<GameModeKind>,
+}
+
+enum GameResult {
+    Finished { score_a: i32, score_b: i32, winner: Option<Team>, drones: Vec<PlayerResult> },
+    Forfeit { loser: Team, reason: String },
+}
+
+impl GameResult {
+    // 1 if side A won, 0.5 for a draw, 0 if side B won
+    fn outcome(&self) -> f64 {
+        match self {
+            GameResult::Finished { winner, .. } => match winner {
+                Some(Team::A) => 1.0,
+                Some(_) => 0.0,
+                None => 0.5,
+            },
+            GameResult::Forfeit { loser, .. } => if *loser == Team::A { 0.0 } else { 1.0 },
+        }
+    }
+}
+
+#[derive(Default)]
+struct Standing {
+    points: f64,
+    wins: u32,
+    draws: u32,
+    losses: u32,
+    score_diff: i64,
+    games_as_a: u32,
+    had_bye: bool,
+}
+
+impl Standing {
+    fn record(&mut self, outcome: f64, score_diff: i64) {
+        self.points += outcome;
+        self.score_diff += score_diff;
+        if outcome == 1.0 {
+            self.wins += 1;
+        } else if outcome == 0.0 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+}
+
+// a bot's connection for the duration of one game
+struct Competitor {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    process: Option<Child>,
+}
+
+impl Competitor {
+    fn connect(bot: &BotEntry, listener: &TcpListener, timeout: Duration) -> io::Result<Competitor> {
+        let (stream, process) = match &bot.source {
+            BotSource::Command(args) => {
+                let mut process = Command::new(&args[0])
+                    .args(&args[1..])
+                    .env(SERVER_ADDR_ENV, listener.local_addr()?.to_string())
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .spawn()?;
+                match accept_with_timeout(listener, &mut process, timeout) {
+                    Ok(stream) => (stream, Some(process)),
+                    Err(e) => {
+                        let _ = process.kill();
+                        let _ = process.wait();
+                        return Err(e);
+                    }
+                }
+            },
+            BotSource::Address(address) => {
+                let address: SocketAddr = address.to_socket_addrs()?
+                    .next()
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "unresolvable address"))?;
+                (TcpStream::connect_timeout(&address, timeout)?, None)
+            },
+        };
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_nodelay(true)?;
+        let mut competitor = Competitor {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            process,
+        };
+        // bots identify themselves exactly as they would to the regular server
+        let identity = competitor.read_line()?;
+        if identity.trim() != "COMPETITOR" {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "bot did not identify as COMPETITOR"));
+        }
+        Ok(competitor)
+    }
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "bot disconnected"));
+        }
+        Ok(line)
+    }
+    fn send(&mut self, msg: &[u8]) -> io::Result<()> {
+        self.writer.write_all(msg)
+    }
+}
+
+impl Drop for Competitor {
+    fn drop(&mut self) {
+        if let Some(process) = &mut self.process {
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+    }
+}
+
+fn accept_with_timeout(listener: &TcpListener, process: &mut Child, timeout: Duration) -> io::Result<TcpStream> {
+    let deadline = Instant::now() + timeout;
+    listener.set_nonblocking(true)?;
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                listener.set_nonblocking(false)?;
+                stream.set_nonblocking(false)?;
+                return Ok(stream);
+            },
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                if process.try_wait()?.is_some() {
+                    return Err(io::Error::new(io::ErrorKind::ConnectionRefused, "bot exited before connecting"));
+                }
+                if Instant::now() > deadline {
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, "bot did not connect in time"));
+                }
+                thread::sleep(Duration::from_millis(10));
+            },
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// `listener` is where the bots of this game connect
+fn play_game(
+    bot_a: &BotEntry,
+    bot_b: &BotEntry,
+    listener: &TcpListener,
+    config: &MatchConfig,
+    observations: &ObservationOptions,
+    timeout: Duration,
+    replay_dir: Option<&Path>,
+) -> GameResult {
+    // connect one side at a time so that the connection order decides nothing
+    let mut competitor_a = match Competitor::connect(bot_a, listener, timeout) {
+        Ok(competitor) => competitor,
+        Err(e) => return GameResult::Forfeit { loser: Team::A, reason: e.to_string() },
+    };
+    let mut competitor_b = match Competitor::connect(bot_b, listener, timeout) {
+        Ok(competitor) => competitor,
+        Err(e) => return GameResult::Forfeit { loser: Team::B, reason: e.to_string() },
+    };
+    let mut gamestate = Gamestate::new(config.clone(), rand::random());
+    for (competitor, team) in [(&mut competitor_a, Team::A), (&mut competitor_b, Team::B)] {
+        if let Err(e) = competitor.send(&broadcast::handshake(&gamestate, team)) {
+            return GameResult::Forfeit { loser: team, reason: e.to_string() };
+        }
+    }
+    // a game that can't be recorded is still played
+    let mut replay = replay_dir.and_then(|replay_dir| {
+        let game_name = format!("{}-vs-{}-{}", bot_a.name, bot_b.name, gamestate.seed())
+            .replace(|c: char| !c.is_ascii_alphanumeric() && c != '-', "_");
+        let path = replay_dir.join(game_name + ".jsonl");
+        ReplayWriter::create(&path, &gamestate)
+            .inspect_err(|e| eprintln!("Could not record replay to {}: {}", path.display(), e))
+            .ok()
+    });
+    loop {
+        if let Err(e) = competitor_a.send(&observations.competitor_message(&gamestate, Team::A)) {
+            return GameResult::Forfeit { loser: Team::A, reason: e.to_string() };
+        }
+        if let Err(e) = competitor_b.send(&observations.competitor_message(&gamestate, Team::B)) {
+            return GameResult::Forfeit { loser: Team::B, reason: e.to_string() };
+        }
+        if gamestate.is_over() {
+            if let Some(replay) = &mut replay {
+                if let Err(e) = replay.finish(&gamestate) {
+                    eprintln!("Could not record replay: {}", e);
+                }
+            }
+            return GameResult::Finished {
+                score_a: gamestate.score(Team::A),
+                score_b: gamestate.score(Team::B),
+                winner: gamestate.winner(),
+                drones: gamestate.result().players,
+            };
+        }
+        let input_a = match competitor_a.read_line() {
+            Ok(input) => input,
+            Err(e) => return GameResult::Forfeit { loser: Team::A, reason: e.to_string() },
+        };
+        let input_b = match competitor_b.read_line() {
+            Ok(input) => input,
+            Err(e) => return GameResult::Forfeit { loser: Team::B, reason: e.to_string() },
+        };
+        gamestate.compute_next_tick(&[input_a, input_b]);
+        if let Some(replay) = &mut replay {
+            if let Err(e) = replay.record(&gamestate) {
+                eprintln!("Could not record replay: {}", e);
+            }
+        }
+    }
+}
+
+// circle method: one bot stays fixed while the others rotate around it
+fn round_robin_rounds(num_bots: usize) -> Vec<Vec<(usize, usize)>> {
+    let mut slots: Vec<Option<usize>> = (0..num_bots).map(Some).collect();
+    if num_bots % 2 == 1 {
+        slots.push(None); // whoever is paired with the empty slot sits out
+    }
+    let num_slots = slots.len();
+    let mut rounds = vec![];
+    for round in 0..num_slots - 1 {
+        let mut pairings = vec![];
+        for i in 0..num_slots / 2 {
+            if let (Some(first), Some(second)) = (slots[i], slots[num_slots - 1 - i]) {
+                // alternate who starts as team A from round to round
+                if round % 2 == 0 {
+                    pairings.push((first, second));
+                } else {
+                    pairings.push((second, first));
+                }
+            }
+        }
+        rounds.push(pairings);
+        slots[1..].rotate_right(1);
+    }
+    rounds
+}
+
+// pairings tried before giving up on avoiding every rematch, the search is exponential
+// when there is no way around a rematch
+const MAX_PAIRING_ATTEMPTS: u32 = 10000;
+
+// pair bots in ranking order, avoiding rematches where at all possible within `budget`
+// pairings tried
+fn pair_without_rematches(
+    order: &[usize],
+    played: &HashSet<(usize, usize)>,
+    budget: &mut u32,
+) -> Option<Vec<(usize, usize)>> {
+    let Some((&first, rest)) = order.split_first() else {
+        return Some(vec![]);
+    };
+    for (i, &opponent) in rest.iter().enumerate() {
+        if *budget == 0 {
+            return None;
+        }
+        *budget -= 1;
+        if played.contains(&(first.min(opponent), first.max(opponent))) {
+            continue;
+        }
+        let remaining: Vec<usize> = rest.iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, &bot)| bot)
+            .collect();
+        if let Some(mut pairings) = pair_without_rematches(&remaining, played, budget) {
+            pairings.insert(0, (first, opponent));
+            return Some(pairings);
+        }
+    }
+    None
+}
+
+// pair each bot in ranking order with the next one it hasn't played yet, or with the
+// next one if it has played them all
+fn pair_greedily(order: &[usize], played: &HashSet<(usize, usize)>) -> Vec<(usize, usize)> {
+    let mut unpaired = order.to_vec();
+    let mut pairings = vec![];
+    while unpaired.len() >= 2 {
+        let first = unpaired.remove(0);
+        let opponent = unpaired.iter()
+            .position(|&opponent| !played.contains(&(first.min(opponent), first.max(opponent))))
+            .unwrap_or(0);
+        pairings.push((first, unpaired.remove(opponent)));
+    }
+    pairings
+}
+
+fn swiss_round(
+    standings: &mut [Standing],
+    ratings: &Ratings,
+    bots: &[BotEntry],
+    played: &HashSet<(usize, usize)>,
+) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..bots.len()).collect();
+    order.sort_by(|&a, &b| {
+        standings[b].points.total_cmp(&standings[a].points)
+            .then(ratings.get(&bots[b].name).rating.total_cmp(&ratings.get(&bots[a].name).rating))
+    });
+    if order.len() % 2 == 1 {
+        // the lowest ranked bot without a bye sits this round out and scores a win
+        let bye_position = order.iter()
+            .rposition(|&bot| !standings[bot].had_bye)
+            .unwrap_or(order.len() - 1);
+        let bye = order.remove(bye_position);
+        println!("{} receives a bye", bots[bye].name);
+        standings[bye].had_bye = true;
+        standings[bye].record(1.0, 0);
+    }
+    let mut budget = MAX_PAIRING_ATTEMPTS;
+    let pairings = pair_without_rematches(&order, played, &mut budget)
+        .unwrap_or_else(|| pair_greedily(&order, played));
+    // whoever has played team A more often starts as team B
+    pairings.into_iter()
+        .map(|(first, second)| {
+            if standings[first].games_as_a > standings[second].games_as_a {
+                (second, first)
+            } else {
+                (first, second)
+            }
+        })
+        .collect()
+}
+
+fn play_pairing(
+    args: &TournamentArgs,
+    config: &MatchConfig,
+    observations: &ObservationOptions,
+    standings: &mut [Standing],
+    ratings: &mut Ratings,
+    first: usize,
+    second: usize,
+) {
+    let timeout = Duration::from_millis(args.response_timeout);
+    for game in 0..args.games_per_pairing {
+        let (a, b) = if game % 2 == 0 { (first, second) } else { (second, first) };
+        let (bot_a, bot_b) = (&args.bots[a], &args.bots[b]);
+        let listener = match TcpListener::bind("127.0.0.1:0") {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("  {} (A) vs {} (B) not played, could not bind a match server: {}", bot_a.name, bot_b.name, e);
+                continue;
+            },
+        };
+        let result = play_game(bot_a, bot_b, &listener, config, observations, timeout, args.replay_dir.as_deref());
+        let outcome = result.outcome();
+        let score_diff = match &result {
+            GameResult::Finished { score_a, score_b, drones, .. } => {
+                println!("  {} (A) {} - {} {} (B)", bot_a.name, score_a, score_b, bot_b.name);
+                for drone in drones {
+                    let name = if drone.player.team == Team::A { &bot_a.name } else { &bot_b.name };
+                    let stats = &drone.stats;
+                    println!(
+                        "    {} drone {}: {} kills, {} deaths, {}/{} shots hit, {:.0} damage dealt, {} friendly fire, {:.1} distance, {} ticks alive",
+                        name, drone.player.index + 1, stats.kills, stats.deaths.total(), stats.hits,
+                        stats.shots_fired, stats.damage_dealt, stats.friendly_fire, stats.distance_traveled,
+                        stats.ticks_alive
+                    );
+                }
+                (score_a - score_b) as i64
+            },
+            GameResult::Forfeit { loser, reason } => {
+                let loser_name = if *loser == Team::A { &bot_a.name } else { &bot_b.name };
+                println!("  {} (A) vs {} (B): {} forfeits ({})", bot_a.name, bot_b.name, loser_name, reason);
+                0
+            },
+        };
+        standings[a].games_as_a += 1;
+        standings[a].record(outcome, score_diff);
+        standings[b].record(1.0 - outcome, -score_diff);
+        ratings.update(args.rating, &bot_a.name, &bot_b.name, outcome);
+    }
+    if let Err(e) = ratings.save(&args.ratings_file) {
+        eprintln!("Could not save ratings to {}: {}", args.ratings_file.display(), e);
+    }
+}
+
+fn print_standings(bots: &[BotEntry], standings: &[Standing], ratings: &Ratings) {
+    let mut order: Vec<usize> = (0..bots.len()).collect();
+    order.sort_by(|&a, &b| {
+        standings[b].points.total_cmp(&standings[a].points)
+            .then(standings[b].score_diff.cmp(&standings[a].score_diff))
+    });
+    let name_width = bots.iter().map(|bot| bot.name.len()).max().unwrap_or(0).max(3);
+    println!(
+        "{:>4}  {:<name_width$}  {:>5}  {:>3}  {:>3}  {:>3}  {:>6}  {:>7}  {:>5}",
+        "Rank", "Bot", "Pts", "W", "D", "L", "Diff", "Rating", "RD"
+    );
+    for (rank, &bot) in order.iter().enumerate() {
+        let standing = &standings[bot];
+        let rating = ratings.get(&bots[bot].name);
+        println!(
+            "{:>4}  {:<name_width$}  {:>5.1}  {:>3}  {:>3}  {:>3}  {:>6}  {:>7.1}  {:>5.1}",
+            rank + 1, bots[bot].name, standing.points, standing.wins, standing.draws,
+            standing.losses, standing.score_diff, rating.rating, rating.deviation
+        );
+    }
+}
+
+/// Competitors are sent observations the way the regular server sends them
+pub fn run(args: TournamentArgs, config: &MatchConfig, observations: &ObservationOptions) {
+    // games are rated as duels between two bots
+    if config.teams != 2 {
+        eprintln!("Tournaments are played between two teams, not {}", config.teams);
+        return;
+    }
+    if let Some(name) = duplicate_name(&args.bots) {
+        eprintln!("Bot name '{}' is entered more than once, give each entry its own name=", name);
+        return;
+    }
+    let mut ratings = match Ratings::load(&args.ratings_file) {
+        Ok(ratings) => ratings,
+        Err(e) => {
+            eprintln!("Could not load ratings from {}: {}", args.ratings_file.display(), e);
+            return;
+        }
+    };
+    if let Some(replay_dir) = &args.replay_dir {
+        if let Err(e) = std::fs::create_dir_all(replay_dir) {
+            eprintln!("Could not create replay directory {}: {}", replay_dir.display(), e);
+            return;
+        }
+    }
+    // the match config of each round
+    let round_config = |round: usize| {
+        let mut config = config.clone();
+        if let Some(&mode) = args.rotate_mode.get(round % args.rotate_mode.len().max(1)) {
+            config.mode = config.mode.with_kind(mode);
+            println!("Round {}: {}", round + 1, mode.to_possible_value().unwrap().get_name());
+        } else {
+            println!("Round {}", round + 1);
+        }
+        config
+    };
+    let num_bots = args.bots.len();
+    let mut standings: Vec<Standing> = (0..num_bots).map(|_| Standing::default()).collect();
+    match args.format {
+        PairingFormat::RoundRobin => {
+            for (round, pairings) in round_robin_rounds(num_bots).into_iter().enumerate() {
+                let config = round_config(round);
+                for (first, second) in pairings {
+                    play_pairing(&args, &config, observations, &mut standings, &mut ratings, first, second);
+                }
+            }
+        },
+        PairingFormat::Swiss => {
+            let default_rounds = (num_bots as f64).log2().ceil() as u32;
+            let num_rounds = args.rounds.unwrap_or(default_rounds).max(1);
+            let mut played: HashSet<(usize, usize)> = HashSet::new();
+            for round in 0..num_rounds {
+                let config = round_config(round as usize);
+                for (first, second) in swiss_round(&mut standings, &ratings, &args.bots, &played) {
+                    played.insert((first.min(second), first.max(second)));
+                    play_pairing(&args, &config, observations, &mut standings, &mut ratings, first, second);
+                }
+            }
+        },
+    }
+    print_standings(&args.bots, &standings, &ratings);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(specs: &[&str]) -> Vec<BotEntry> {
+        specs.iter().map(|spec| BotEntry::parse(spec).unwrap()).collect()
+    }
+
+    #[test]
+    fn bots_need_names_of_their_own() {
+        assert_eq!(duplicate_name(&entries(&["a=./bot", "b=./bot", "tcp://host:1"])), None);
+        assert_eq!(duplicate_name(&entries(&["./bot --fast", "x=tcp://host:1", "./bot --fast"])), Some("./bot --fast"));
+        assert_eq!(duplicate_name(&entries(&["x=./bot", "x=./other"])), Some("x"));
+    }
+}
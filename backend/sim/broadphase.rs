@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+// Broadphase for collision checks: every tick, everything that can collide is put into a
+// uniform grid by the box around the path it moved along this tick, so that the exact
+// swept checks only run between things sharing a cell instead of between all pairs.
+
+// side length of a grid cell, about as wide as a drone plus a few ticks of movement
+const CELL_SIZE: f32 = 4.0;
+
+/// Axis-aligned bounding box
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Aabb {
+    pub min: [f32;3],
+    pub max: [f32;3],
+}
+
+impl Aabb {
+    /// Box around a sphere of `radius` moving from `start` to `end`
+    pub(crate) fn swept(start: &[f32;3], end: &[f32;3], radius: f32) -> Aabb {
+        Aabb {
+            min: [
+                start[0].min(end[0]) - radius,
+                start[1].min(end[1]) - radius,
+                start[2].min(end[2]) - radius,
+            ],
+            max: [
+                start[0].max(end[0]) + radius,
+                start[1].max(end[1]) + radius,
+                start[2].max(end[2]) + radius,
+            ],
+        }
+    }
+    // range of grid cells the box touches along each axis
+    fn cells(&self) -> [(i32, i32);3] {
+        let cell = |value: f32| (value / CELL_SIZE).floor() as i32;
+        [
+            (cell(self.min[0]), cell(self.max[0])),
+            (cell(self.min[1]), cell(self.max[1])),
+            (cell(self.min[2]), cell(self.max[2])),
+        ]
+    }
+}
+
+/// Uniform grid of items keyed by their bounding boxes
+pub(crate) struct Grid<T> {
+    cells: HashMap<[i32;3], Vec<T>>,
+}
+
+impl<T: Copy + Ord> Grid<T> {
+    pub(crate) fn new(items: impl IntoIterator<Item = (T, Aabb)>) -> Grid<T> {
+        let mut grid = Grid { cells: HashMap::new() };
+        for (item, aabb) in items {
+            grid.insert(item, &aabb);
+        }
+        grid
+    }
+    pub(crate) fn insert(&mut self, item: T, aabb: &Aabb) {
+        let [(x0, x1), (y0, y1), (z0, z1)] = aabb.cells();
+        for x in x0..=x1 {
+            for y in y0..=y1 {
+                for z in z0..=z1 {
+                    self.cells.entry([x, y, z]).or_default().push(item);
+                }
+            }
+        }
+    }
+    /// Items that share a cell with `aabb`, each once and in order, these may but need
+    /// not overlap it
+    pub(crate) fn query(&self, aabb: &Aabb) -> Vec<T> {
+        let [(x0, x1), (y0, y1), (z0, z1)] = aabb.cells();
+        let mut found = vec![];
+        for x in x0..=x1 {
+            for y in y0..=y1 {
+                for z in z0..=z1 {
+                    if let Some(items) = self.cells.get(&[x, y, z]) {
+                        found.extend_from_slice(items);
+                    }
+                }
+            }
+        }
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+}
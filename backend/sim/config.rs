@@ -0,0 +1,646 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use clap::ValueEnum;
+use serde::{Serialize, Deserialize};
+use crate::events::PlayerId;
+use crate::fog::FogOfWarConfig;
+use crate::gamestate::Team;
+use crate::reward::RewardConfig;
+
+/// Something happening in a match that ends it before `max_game_ticks`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TerminalEvent {
+    /// Any drone dies
+    AnyDeath,
+    /// Every drone of a team is dead at once
+    TeamWipe,
+}
+
+/// How drones respond to their controls, accelerations are per game tick at full input
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlightModel {
+    /// Acceleration along the drone's heading
+    pub thrust: f32,
+    /// Acceleration up and down
+    pub vertical_thrust: f32,
+    /// Acceleration sideways, 0 disables strafing
+    pub strafe_thrust: f32,
+    /// Fraction of the velocity lost every game tick
+    pub linear_drag: f32,
+    /// Fraction of the velocity lost every game tick per unit of speed
+    pub quadratic_drag: f32,
+    /// Speed drones can't exceed, in distance per game tick
+    pub max_speed: Option<f32>,
+    /// Fastest turn in radians per game tick
+    pub turn_rate: f32,
+    /// Fastest change of pitch in radians per game tick
+    pub pitch_rate: f32,
+    /// Fastest roll in radians per game tick
+    pub roll_rate: f32,
+}
+
+impl Default for FlightModel {
+    fn default() -> FlightModel {
+        FlightModel {
+            thrust: 0.02,
+            vertical_thrust: 0.02,
+            strafe_thrust: 0.0,
+            linear_drag: 0.05,
+            quadratic_drag: 0.0,
+            max_speed: Some(0.2),
+            turn_rate: 0.1,
+            pitch_rate: 0.1,
+            roll_rate: 0.1,
+        }
+    }
+}
+
+/// When obstacles enter the arena
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SpawnPattern {
+    /// One obstacle about every `interval` game ticks, from any side
+    Stream { interval: u32 },
+    /// `count` obstacles at once every `interval` game ticks, from any side
+    Waves { interval: u32, count: u32 },
+    /// One obstacle every `interval` game ticks for `duration` game ticks, all from the
+    /// same side, then a calm of `calm` game ticks before the next storm from another side
+    Storm { interval: u32, duration: u32, calm: u32 },
+}
+
+/// Obstacles spawn outside the arena and fly through it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ObstacleConfig {
+    pub pattern: SpawnPattern,
+    /// How many times more often obstacles spawn by the end of the match than at its
+    /// start, increasing steadily in between. 1 keeps the rate constant
+    pub ramp_up: f32,
+    pub min_radius: f32,
+    pub max_radius: f32,
+    /// Distance per game tick
+    pub min_speed: f32,
+    pub max_speed: f32,
+}
+
+impl Default for ObstacleConfig {
+    fn default() -> ObstacleConfig {
+        ObstacleConfig {
+            pattern: SpawnPattern::Stream { interval: 70 },
+            ramp_up: 1.0,
+            min_radius: 1.5,
+            max_radius: 5.0,
+            min_speed: 0.1,
+            max_speed: 0.3,
+        }
+    }
+}
+
+/// A box drones may spawn in, in arena coordinates
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpawnZone {
+    pub min: [f32;3],
+    pub max: [f32;3],
+}
+
+/// Drones spawn at the point of their spawn zone furthest from other drones, obstacles
+/// and incoming bullets
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpawnConfig {
+    /// Where each team spawns, teams without a zone spawn anywhere away from the edge
+    /// of the arena. Zones are clipped to that same region
+    pub zones: HashMap<Team, SpawnZone>,
+    /// Candidate points tried along each axis of the zone, the number of candidates is
+    /// this cubed
+    pub candidates: u32,
+    /// Game ticks ahead that obstacles and bullets are followed along their current
+    /// velocity, spawnpoints keep clear of their whole path
+    pub lookahead: u32,
+}
+
+impl Default for SpawnConfig {
+    fn default() -> SpawnConfig {
+        SpawnConfig {
+            zones: HashMap::new(),
+            candidates: 8,
+            lookahead: 60,
+        }
+    }
+}
+
+/// What happens to drones at the edge of the arena
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BoundaryPolicy {
+    /// Drones leaving the arena die, losing the out-of-bounds penalty
+    #[default]
+    Kill,
+    /// Drones bounce off the walls without losing speed
+    Bounce,
+    /// Drones leaving through one wall come back in through the opposite one
+    Wrap,
+    /// Drones closer than `margin` to a wall are pushed back, harder the further they go
+    ForceField { strength: f32, margin: f32 },
+}
+
+/// How bullets fly
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BulletConfig {
+    /// Distance traveled per game tick
+    pub speed: f32,
+    /// Downward acceleration per game tick, 0 for perfectly straight shots
+    pub gravity: f32,
+    /// Distance after which a bullet despawns
+    pub max_range: Option<f32>,
+    /// Game ticks after which a bullet despawns
+    pub max_lifetime: Option<u32>,
+}
+
+impl Default for BulletConfig {
+    fn default() -> BulletConfig {
+        BulletConfig {
+            speed: 0.25,
+            gravity: 0.0,
+            max_range: None,
+            max_lifetime: None,
+        }
+    }
+}
+
+/// A kind of weapon drones can carry
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeaponKind {
+    /// Fires bullets, see [`BulletConfig`]
+    Gun,
+    /// Hits the first drone or obstacle along its beam instantly
+    Laser,
+    /// Fires slow missiles that steer towards the closest enemy drone
+    Missile,
+    /// Drops mines behind the drone that stay where they are until an enemy comes close
+    Mine,
+}
+
+/// Ammo and timing of a weapon, each drone's weapons keep their own ammo and timers
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AmmoConfig {
+    /// Shots the weapon holds
+    pub max: u32,
+    /// Game ticks to reload one shot
+    pub reload_ticks: u32,
+    /// Game ticks between shots
+    pub cooldown_ticks: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GunConfig {
+    pub ammo: AmmoConfig,
+}
+
+impl Default for GunConfig {
+    fn default() -> GunConfig {
+        GunConfig {
+            ammo: AmmoConfig { max: 3, reload_ticks: 60, cooldown_ticks: 10 },
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LaserConfig {
+    pub ammo: AmmoConfig,
+    /// Length of the beam
+    pub range: f32,
+    pub damage: f32,
+}
+
+impl Default for LaserConfig {
+    fn default() -> LaserConfig {
+        LaserConfig {
+            ammo: AmmoConfig { max: 2, reload_ticks: 90, cooldown_ticks: 30 },
+            range: 30.0,
+            damage: 25.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MissileConfig {
+    pub ammo: AmmoConfig,
+    /// Distance traveled per game tick
+    pub speed: f32,
+    /// Fastest turn towards the target in radians per game tick
+    pub turn_rate: f32,
+    pub damage: f32,
+    /// Game ticks after which a missile explodes by itself
+    pub lifetime: u32,
+}
+
+impl Default for MissileConfig {
+    fn default() -> MissileConfig {
+        MissileConfig {
+            ammo: AmmoConfig { max: 2, reload_ticks: 240, cooldown_ticks: 60 },
+            speed: 0.15,
+            turn_rate: 0.04,
+            damage: 60.0,
+            lifetime: 400,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MineConfig {
+    pub ammo: AmmoConfig,
+    /// Game ticks after being dropped before a mine can go off
+    pub arm_ticks: u32,
+    /// Distance from the mine at which an enemy drone sets it off
+    pub trigger_radius: f32,
+    /// Distance from the mine within which every drone takes damage when it goes off
+    pub blast_radius: f32,
+    pub damage: f32,
+    /// Mines a drone can have out at once, dropping another removes its oldest
+    pub max_per_drone: usize,
+}
+
+impl Default for MineConfig {
+    fn default() -> MineConfig {
+        MineConfig {
+            ammo: AmmoConfig { max: 2, reload_ticks: 300, cooldown_ticks: 30 },
+            arm_ticks: 60,
+            trigger_radius: 2.5,
+            blast_radius: 3.0,
+            damage: 70.0,
+            max_per_drone: 3,
+        }
+    }
+}
+
+/// What weapons drones carry and how each of them works
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WeaponsConfig {
+    /// Weapons every drone carries, controls pick one by its index in here
+    pub loadout: Vec<WeaponKind>,
+    /// Loadouts of particular drones instead of `loadout`, indexed by drone within the team
+    pub drone_loadouts: HashMap<Team, Vec<Vec<WeaponKind>>>,
+    pub gun: GunConfig,
+    pub laser: LaserConfig,
+    pub missile: MissileConfig,
+    pub mine: MineConfig,
+}
+
+impl Default for WeaponsConfig {
+    fn default() -> WeaponsConfig {
+        WeaponsConfig {
+            loadout: vec![WeaponKind::Gun],
+            drone_loadouts: HashMap::new(),
+            gun: GunConfig::default(),
+            laser: LaserConfig::default(),
+            missile: MissileConfig::default(),
+            mine: MineConfig::default(),
+        }
+    }
+}
+
+impl WeaponsConfig {
+    /// Weapons a drone carries, in the order controls pick them
+    pub fn loadout(&self, drone: PlayerId) -> &[WeaponKind] {
+        self.drone_loadouts.get(&drone.team)
+            .and_then(|loadouts| loadouts.get(drone.index))
+            .unwrap_or(&self.loadout)
+    }
+    pub fn ammo(&self, kind: WeaponKind) -> &AmmoConfig {
+        match kind {
+            WeaponKind::Gun => &self.gun.ammo,
+            WeaponKind::Laser => &self.laser.ammo,
+            WeaponKind::Missile => &self.missile.ammo,
+            WeaponKind::Mine => &self.mine.ammo,
+        }
+    }
+}
+
+/// Hit points of drones and how they lose them, drones die once their health runs out
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    /// Health of a freshly spawned drone
+    pub max_health: f32,
+    /// Shield of a freshly spawned drone, damage takes the shield down before the
+    /// health. 0 disables shields
+    pub max_shield: f32,
+    /// Shield regained per game tick
+    pub shield_regen: f32,
+    /// Game ticks after the last damage taken before the shield starts regenerating
+    pub shield_regen_delay: u32,
+    /// Damage dealt by a bullet hit, other weapons have theirs in [`WeaponsConfig`]
+    pub bullet_damage: f32,
+    /// Damage dealt by hitting an obstacle, per unit of relative speed in distance per
+    /// game tick
+    pub obstacle_damage: f32,
+    /// Damage dealt to each of two colliding drones, per unit of relative speed in
+    /// distance per game tick
+    pub collision_damage: f32,
+}
+
+impl Default for HealthConfig {
+    fn default() -> HealthConfig {
+        HealthConfig {
+            max_health: 100.0,
+            max_shield: 0.0,
+            shield_regen: 0.5,
+            shield_regen_delay: 120,
+            bullet_damage: 35.0,
+            obstacle_damage: 200.0,
+            collision_damage: 200.0,
+        }
+    }
+}
+
+/// What an item does to the drone that picks it up
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PickupKind {
+    /// Refills the ammo of all the drone's weapons
+    Ammo,
+    /// Restores `amount` health, up to the maximum
+    Health { amount: f32 },
+    /// Adds `amount` to the shield, which may take it over the maximum. Shields only
+    /// regenerate up to the maximum
+    Shield { amount: f32 },
+    /// Multiplies thrust and top speed by `factor` for `duration` game ticks
+    SpeedBoost { factor: f32, duration: u32 },
+    /// Shortens the time between shots by `factor` for `duration` game ticks
+    RapidFire { factor: f32, duration: u32 },
+}
+
+/// Items drones collect by flying into them
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PickupConfig {
+    /// What kinds of items spawn, picked at random. Listing a kind several times makes
+    /// it more common
+    pub kinds: Vec<PickupKind>,
+    /// Points items spawn at in arena coordinates, one item per point at a time. Without
+    /// any, items spawn at random points away from the edge of the arena
+    pub spawn_points: Vec<[f32;3]>,
+    /// Game ticks between spawns
+    pub interval: u32,
+    /// Most items in the arena at once
+    pub max_items: usize,
+    /// Drones touching an item collect it
+    pub radius: f32,
+}
+
+impl Default for PickupConfig {
+    fn default() -> PickupConfig {
+        PickupConfig {
+            kinds: vec![
+                PickupKind::Ammo,
+                PickupKind::Health { amount: 50.0 },
+                PickupKind::Shield { amount: 50.0 },
+                PickupKind::SpeedBoost { factor: 1.5, duration: 300 },
+                PickupKind::RapidFire { factor: 2.0, duration: 300 },
+            ],
+            spawn_points: vec![],
+            interval: 300,
+            max_items: 4,
+            radius: 0.5,
+        }
+    }
+}
+
+/// What happens to a bullet that hits an obstacle
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulletObstacle {
+    /// The bullet is stopped and despawned
+    #[default]
+    Block,
+    /// The bullet is stopped and despawned, pushing the obstacle along a little
+    Absorb,
+    /// The bullet bounces off the obstacle's surface
+    Reflect,
+    /// The bullet flies straight through
+    Pass,
+}
+
+/// Points won or lost by a team, penalties are subtracted from the team's score
+///
+/// Points only change hands when a drone dies: hits that a drone survives, whether from
+/// a teammate's shot or a collision, cost nothing.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoringRules {
+    /// Won by the shooter's team for shooting down an enemy drone
+    pub kill_reward: i32,
+    /// Lost by a drone's team when the drone is shot down, whoever fired
+    pub death_penalty: i32,
+    /// Lost by the shooter's team for shooting down a teammate, on top of the death penalty
+    pub friendly_fire_penalty: i32,
+    /// Lost by a drone's team when the drone dies leaving the arena, only with the kill
+    /// boundary policy
+    pub out_of_bounds_penalty: i32,
+    /// Lost by a drone's team when the drone is killed by hitting an obstacle
+    pub obstacle_death_penalty: i32,
+    /// Lost by a drone's team when the drone is killed colliding with an enemy drone
+    pub enemy_collision_penalty: i32,
+    /// Lost by a drone's team when the drone is killed colliding with a teammate
+    pub teammate_collision_penalty: i32,
+}
+
+impl Default for ScoringRules {
+    fn default() -> ScoringRules {
+        ScoringRules {
+            kill_reward: 2,
+            death_penalty: 1,
+            friendly_fire_penalty: 0,
+            out_of_bounds_penalty: 0,
+            obstacle_death_penalty: 1,
+            enemy_collision_penalty: 0,
+            teammate_collision_penalty: 0,
+        }
+    }
+}
+
+/// Settings of capture the flag
+///
+/// Every team has a flag at its base. A drone takes an enemy flag by touching it, and
+/// captures it by bringing it to its own base while its own flag is there. A drone that
+/// dies drops the flag it carries, which goes back to its base when a drone of its team
+/// touches it or after lying there for a while.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureTheFlagConfig {
+    /// Where each team's base is, teams left out get bases spread evenly around the
+    /// middle of the arena, like the turns of mirrored observations
+    pub bases: HashMap<Team, [f32;3]>,
+    /// How close a drone has to come to a flag or to its base
+    pub radius: f32,
+    /// Won by the capturing drone's team
+    pub capture_reward: i32,
+    /// Captures that win the match, the match never ends by captures without one
+    pub captures_to_win: Option<u32>,
+    /// Game ticks a dropped flag lies where it fell before going back to its base
+    pub return_ticks: u32,
+}
+
+impl Default for CaptureTheFlagConfig {
+    fn default() -> CaptureTheFlagConfig {
+        CaptureTheFlagConfig {
+            bases: HashMap::new(),
+            radius: 2.0,
+            capture_reward: 5,
+            captures_to_win: Some(3),
+            return_ticks: 300,
+        }
+    }
+}
+
+/// Settings of king of the hill
+///
+/// A team holds the hill while it has living drones inside it and no other team does,
+/// and scores for as long as it holds it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KingOfTheHillConfig {
+    pub center: [f32;3],
+    pub radius: f32,
+    /// Game ticks of holding the hill per point
+    pub interval: u32,
+    /// Won by the holding team every `interval` game ticks
+    pub points: i32,
+    /// Game ticks of holding the hill in total that win the match, the match never ends by
+    /// holding it without one
+    pub hold_to_win: Option<u32>,
+}
+
+impl Default for KingOfTheHillConfig {
+    fn default() -> KingOfTheHillConfig {
+        KingOfTheHillConfig {
+            center: [0.0, 0.0, 0.0],
+            radius: 4.0,
+            interval: 20,
+            points: 1,
+            hold_to_win: None,
+        }
+    }
+}
+
+/// What a match is played for, on top of the scoring rules which apply in every mode
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GameModeConfig {
+    /// Shoot down enemy drones, the scoring rules are all there is to it
+    #[default]
+    TeamDeathmatch,
+    CaptureTheFlag(CaptureTheFlagConfig),
+    KingOfTheHill(KingOfTheHillConfig),
+}
+
+/// Game modes by name
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameModeKind {
+    TeamDeathmatch,
+    CaptureTheFlag,
+    KingOfTheHill,
+}
+
+impl GameModeConfig {
+    pub fn kind(&self) -> GameModeKind {
+        match self {
+            GameModeConfig::TeamDeathmatch => GameModeKind::TeamDeathmatch,
+            GameModeConfig::CaptureTheFlag(_) => GameModeKind::CaptureTheFlag,
+            GameModeConfig::KingOfTheHill(_) => GameModeKind::KingOfTheHill,
+        }
+    }
+    /// The mode of `kind`: this one if it already is, otherwise with default settings
+    pub fn with_kind(&self, kind: GameModeKind) -> GameModeConfig {
+        if self.kind() == kind {
+            return self.clone();
+        }
+        match kind {
+            GameModeKind::TeamDeathmatch => GameModeConfig::TeamDeathmatch,
+            GameModeKind::CaptureTheFlag => GameModeConfig::CaptureTheFlag(CaptureTheFlagConfig::default()),
+            GameModeKind::KingOfTheHill => GameModeConfig::KingOfTheHill(KingOfTheHillConfig::default()),
+        }
+    }
+}
+
+/// Rules of a match, loadable from a JSON file
+///
+/// Every field has a default, so a config file only needs the fields it changes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MatchConfig {
+    /// Game ticks until the match is over
+    pub max_game_ticks: u32,
+    /// Number of competing teams, each played by its own competitor
+    pub teams: usize,
+    /// Drones on each team, a team size of 1 makes a free-for-all
+    pub team_size: usize,
+    /// Events that end the match early
+    pub terminal_events: Vec<TerminalEvent>,
+    /// Team score that ends the match early
+    pub score_limit: Option<i32>,
+    /// Objective of the match
+    pub mode: GameModeConfig,
+    /// Limit what each team can observe to what its drones can sense
+    pub fog_of_war: Option<FogOfWarConfig>,
+    pub flight_model: FlightModel,
+    pub boundary: BoundaryPolicy,
+    pub obstacles: ObstacleConfig,
+    pub spawning: SpawnConfig,
+    pub bullets: BulletConfig,
+    pub weapons: WeaponsConfig,
+    pub health: HealthConfig,
+    /// Items in the arena, none without this
+    pub pickups: Option<PickupConfig>,
+    /// How obstacles stop bullets
+    pub bullet_obstacle: BulletObstacle,
+    /// Points won and lost during the match
+    pub scoring: ScoringRules,
+    /// Reward function used in training
+    pub reward: RewardConfig,
+}
+
+impl Default for MatchConfig {
+    fn default() -> MatchConfig {
+        MatchConfig {
+            max_game_ticks: 10000,
+            teams: 2,
+            team_size: 2,
+            terminal_events: vec![],
+            score_limit: None,
+            mode: GameModeConfig::default(),
+            fog_of_war: None,
+            flight_model: FlightModel::default(),
+            boundary: BoundaryPolicy::default(),
+            obstacles: ObstacleConfig::default(),
+            spawning: SpawnConfig::default(),
+            bullets: BulletConfig::default(),
+            weapons: WeaponsConfig::default(),
+            health: HealthConfig::default(),
+            pickups: None,
+            bullet_obstacle: BulletObstacle::default(),
+            scoring: ScoringRules::default(),
+            reward: RewardConfig::default(),
+        }
+    }
+}
+
+impl MatchConfig {
+    pub fn load(path: &Path) -> io::Result<MatchConfig> {
+        let contents = fs::read_to_string(path)?;
+        serde_json::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
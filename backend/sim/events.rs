@@ -0,0 +1,84 @@
+use serde::Serialize;
+use crate::config::PickupKind;
+use crate::gamestate::Team;
+
+/// Identifies a drone: its team and its index within the team
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize)]
+pub struct PlayerId {
+    pub team: Team,
+    pub index: usize,
+}
+
+/// What damaged or killed a drone
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeathCause {
+    Bullet,
+    Laser,
+    Missile,
+    Mine,
+    Obstacle,
+    Collision,
+    OutOfBounds,
+}
+
+impl DeathCause {
+    /// Whether a drone's weapon caused it
+    pub fn is_weapon(&self) -> bool {
+        matches!(self, DeathCause::Bullet | DeathCause::Laser | DeathCause::Missile | DeathCause::Mine)
+    }
+}
+
+/// Something that happened during a tick
+///
+/// A [`crate::Gamestate`] keeps the events of the last computed tick, in the order they
+/// happened. Some events come together: a drone shot by a teammate produces both
+/// `PlayerDamaged` and `FriendlyFire`, followed by `PlayerKilled` if the hit was fatal.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type")]
+pub enum Event {
+    BulletFired { shooter: PlayerId, bullet: u64 },
+    /// The beam went from the shooter to the first drone or obstacle in its way, or as
+    /// far as it reaches
+    LaserFired { shooter: PlayerId, from: [f32;3], to: [f32;3] },
+    MissileFired { shooter: PlayerId, missile: u64 },
+    /// The missile hit something or ran out of time, and is gone
+    MissileExploded { shooter: PlayerId, missile: u64 },
+    MineLaid { owner: PlayerId, mine: u64 },
+    /// The mine went off and is gone, drones in the blast are damaged separately
+    MineExploded { owner: PlayerId, mine: u64 },
+    /// `damage` is the total taken, `absorbed` the part of it the shield took. `attacker`
+    /// is the drone whose weapon hit or that was collided with
+    PlayerDamaged {
+        victim: PlayerId,
+        attacker: Option<PlayerId>,
+        cause: DeathCause,
+        damage: f32,
+        absorbed: f32,
+    },
+    /// `killer` is the drone whose weapon hit or that was collided with
+    PlayerKilled { victim: PlayerId, killer: Option<PlayerId>, cause: DeathCause },
+    FriendlyFire { victim: PlayerId, shooter: PlayerId },
+    /// Whether the bullet survives depends on [`crate::BulletObstacle`]
+    BulletHitObstacle { bullet: u64, obstacle: u64 },
+    ObstacleSpawned { obstacle: u64 },
+    PlayerRespawned { player: PlayerId },
+    PickupSpawned { pickup: u64 },
+    /// The item is gone once collected, `kind` is what it was
+    PickupCollected { player: PlayerId, pickup: u64, kind: PickupKind },
+    /// The drone took `flag`, the flag of that team, from its base or from where it lay
+    FlagTaken { player: PlayerId, flag: Team },
+    /// The drone carrying `flag` died and dropped it where it was
+    FlagDropped { player: PlayerId, flag: Team },
+    /// `flag` went back to its base, touched by `player` of its own team or after lying
+    /// dropped for too long
+    FlagReturned { player: Option<PlayerId>, flag: Team },
+    /// The drone brought `flag` to its own base
+    FlagCaptured { player: PlayerId, flag: Team },
+    /// `holder` is the team now holding the hill, `None` once nobody does
+    HillChanged { holder: Option<Team> },
+    /// The drone crossed the edge of the arena, see [`crate::BoundaryPolicy`] for what follows
+    OutOfBounds { player: PlayerId },
+    /// Points won or lost by the drone's team because of the drone
+    ScoreChanged { player: PlayerId, points: i32 },
+}
@@ -0,0 +1,148 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Serialize, Deserialize};
+use crate::gamestate::{Bullet, Mine, Missile, Obstacle, Pickup, Player, Team};
+use crate::vec3::{distance, distance_to_segment};
+
+// Partial observability: a team only sees what its living drones can sense. Anything
+// within sensor range is detected regardless of cover, anything further away but within
+// sight range is seen unless an obstacle blocks the line of sight.
+
+/// Sensor ranges used when fog of war is enabled
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FogOfWarConfig {
+    /// Distance within which drones detect everything, even behind obstacles
+    pub sensor_range: f32,
+    /// Distance within which drones see anything not hidden behind an obstacle
+    pub sight_range: f32,
+}
+
+impl Default for FogOfWarConfig {
+    fn default() -> FogOfWarConfig {
+        FogOfWarConfig {
+            sensor_range: 8.0,
+            sight_range: 30.0,
+        }
+    }
+}
+
+/// Where a drone was when a team last saw it
+#[derive(Clone, Debug, Serialize)]
+pub struct LastSeen {
+    pub position: [f32;3],
+    pub velocity: [f32;3],
+    pub tick: u32,
+}
+
+/// What one team can currently see
+#[derive(Clone, Debug, Default)]
+pub struct Visibility {
+    /// Indexed like [`crate::Gamestate::players`], a team always sees its own drones
+    pub drones: Vec<bool>,
+    pub obstacles: HashSet<u64>,
+    pub bullets: HashSet<u64>,
+    pub missiles: HashSet<u64>,
+    pub mines: HashSet<u64>,
+    pub pickups: HashSet<u64>,
+    /// Where each enemy drone was last seen, remembered even once it's out of sight
+    /// again. Indexed like [`crate::Gamestate::players`], `None` for the team's own drones
+    pub last_seen: Vec<Option<LastSeen>>,
+}
+
+/// Everything in the arena that can be seen
+pub(crate) struct Scene<'a> {
+    pub players: &'a [Player],
+    pub obstacles: &'a [&'a Obstacle],
+    pub bullets: &'a [&'a Bullet],
+    pub missiles: &'a [&'a Missile],
+    pub mines: &'a [&'a Mine],
+    pub pickups: &'a [&'a Pickup],
+}
+
+struct Sensors<'a> {
+    config: &'a FogOfWarConfig,
+    observers: Vec<[f32;3]>,
+    obstacles: &'a [&'a Obstacle],
+}
+
+impl Sensors<'_> {
+    // the target obstacle itself, if any, doesn't block the view of itself
+    fn can_see(&self, target: &[f32;3], target_radius: f32, target_guid: Option<u64>) -> bool {
+        self.observers.iter().any(|observer| {
+            let target_distance = distance(observer, target) - target_radius;
+            if target_distance <= self.config.sensor_range {
+                return true;
+            }
+            target_distance <= self.config.sight_range && !self.obstacles.iter().any(|obstacle| {
+                Some(obstacle.guid) != target_guid
+                    && distance_to_segment(&obstacle.position, observer, target) < obstacle.radius
+            })
+        })
+    }
+}
+
+// what each team can see this tick, keeping the last sightings from `previous`
+pub(crate) fn compute_visibility(
+    config: &FogOfWarConfig,
+    teams: usize,
+    scene: &Scene,
+    tick: u32,
+    previous: Option<&HashMap<Team, Visibility>>,
+) -> HashMap<Team, Visibility> {
+    let Scene { players, obstacles, bullets, missiles, mines, pickups } = *scene;
+    let mut visibility = HashMap::new();
+    for team in Team::all(teams) {
+        let sensors = Sensors {
+            config,
+            // dead drones don't sense anything
+            observers: players.iter()
+                .filter(|player| player.team == team && !player.is_dead)
+                .map(|player| player.position)
+                .collect(),
+            obstacles,
+        };
+        let mut team_visibility = previous
+            .and_then(|previous| previous.get(&team))
+            .cloned()
+            .unwrap_or_default();
+        team_visibility.drones.resize(players.len(), false);
+        team_visibility.last_seen.resize(players.len(), None);
+        for (i, player) in players.iter().enumerate() {
+            if player.team == team {
+                team_visibility.drones[i] = true;
+                continue;
+            }
+            team_visibility.drones[i] = !player.is_dead && sensors.can_see(&player.position, 0.0, None);
+            if team_visibility.drones[i] {
+                team_visibility.last_seen[i] = Some(LastSeen {
+                    position: player.position,
+                    velocity: player.velocity,
+                    tick,
+                });
+            }
+        }
+        team_visibility.obstacles = obstacles.iter()
+            .filter(|obstacle| sensors.can_see(&obstacle.position, obstacle.radius, Some(obstacle.guid)))
+            .map(|obstacle| obstacle.guid)
+            .collect();
+        // a team always knows where its own bullets, missiles and mines are
+        team_visibility.bullets = bullets.iter()
+            .filter(|bullet| bullet.team == team || sensors.can_see(&bullet.position, 0.0, None))
+            .map(|bullet| bullet.guid)
+            .collect();
+        team_visibility.missiles = missiles.iter()
+            .filter(|missile| missile.team == team || sensors.can_see(&missile.position, 0.0, None))
+            .map(|missile| missile.guid)
+            .collect();
+        team_visibility.mines = mines.iter()
+            .filter(|mine| mine.team == team || sensors.can_see(&mine.position, 0.0, None))
+            .map(|mine| mine.guid)
+            .collect();
+        team_visibility.pickups = pickups.iter()
+            .filter(|item| sensors.can_see(&item.position, item.radius, None))
+            .map(|item| item.guid)
+            .collect();
+        visibility.insert(team, team_visibility);
+    }
+    visibility
+}
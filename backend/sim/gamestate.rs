@@ -0,0 +1,1844 @@
+use std::{collections::HashMap, f32::consts::{FRAC_PI_2, TAU}, fmt, str::FromStr};
+use rand::{SeedableRng, rngs::StdRng};
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use crate::broadphase::{Aabb, Grid};
+use crate::config::{
+    BoundaryPolicy, BulletObstacle, FlightModel, MatchConfig, PickupKind, ScoringRules,
+    TerminalEvent, WeaponKind, WeaponsConfig,
+};
+use crate::events::{DeathCause, Event, PlayerId};
+use crate::fog::{self, Scene, Visibility};
+use crate::modes::{self, Arena, GameMode, Objectives};
+use crate::obstacles::ObstacleSpawner;
+use crate::pickups::{self, PickupSpawner};
+use crate::spawn::{self, Threats};
+use crate::stats::{MatchResult, PlayerResult, PlayerStats};
+use crate::vec3::{add_vec3, direction_vec3, distance, dot_vec3, length_vec3};
+use crate::weapons;
+
+// game area bounded by a cube, this value is half of said cube's side length
+pub(crate) const GAME_AREA_SIZE: f32 = 20.0;
+// area outside of which obstacles are deleted
+pub(crate) const OBSTACLE_AREA_SIZE: f32 = GAME_AREA_SIZE * 1.5;
+pub(crate) const PLAYER_RADIUS: f32 = 1.0;
+pub(crate) const MAX_PLAYER_RESPAWN_TIMER: u32 = 80;
+// share of a bullet's velocity passed on to an obstacle of radius 1 that absorbs it
+const ABSORBED_MOMENTUM: f32 = 0.1;
+// bounces off obstacles a bullet can make in one tick
+const MAX_BULLET_REFLECTIONS: usize = 4;
+// space left between things pushed apart after a collision, so they don't touch again
+const CONTACT_GAP: f32 = 0.01;
+
+// earliest point along the segment from v1 to v2 that is inside the sphere, as a
+// fraction of the way from v1 to v2, or None if the segment misses the sphere
+fn intersect_sphere_lineseg(
+    center: &[f32;3],
+    radius: &f32,
+    v1: &[f32;3],
+    v2: &[f32;3]
+) -> Option<f32> {
+    // solved using parametric equation for a line: l(t) = v1 + (v2 - v1)*t
+    // we plug this into the sphere's equation |l(t) - center|^2 = r^2
+    // this yields a quadratic a*t^2 + b*t + c = 0 which we solve for t
+    // we have intersection if the interval between the solutions overlaps 0 <= t <= 1
+    let direction = [v2[0] - v1[0], v2[1] - v1[1], v2[2] - v1[2]];
+    let from_center = [v1[0] - center[0], v1[1] - center[1], v1[2] - center[2]];
+    let c = dot_vec3(&from_center, &from_center) - radius.powi(2);
+    if c <= 0.0 { return Some(0.0); } // starts inside (or touching) the sphere
+    let a = dot_vec3(&direction, &direction);
+    if a == 0.0 { return None; } // segment is a single point, outside the sphere
+    let b = 2.0 * dot_vec3(&from_center, &direction);
+    let discriminant = b.powi(2) - 4.0 * a * c;
+    if discriminant < 0.0 { return None; } // the line misses the sphere entirely
+    // c > 0 so both solutions have the same sign, the smaller one is the entry point
+    let t_enter = (-b - discriminant.sqrt()) / (2.0 * a);
+    (0.0..=1.0).contains(&t_enter).then_some(t_enter)
+}
+
+// earliest time during a tick at which two spheres, each moving in a straight line from
+// its start to its end position, touch, as a fraction of the tick
+fn sweep_spheres(
+    start_1: &[f32;3],
+    end_1: &[f32;3],
+    radius_1: &f32,
+    start_2: &[f32;3],
+    end_2: &[f32;3],
+    radius_2: &f32
+) -> Option<f32> {
+    // in the frame of reference of sphere 2, sphere 1 moves along a single segment
+    let relative_start = [start_1[0] - start_2[0], start_1[1] - start_2[1], start_1[2] - start_2[2]];
+    let relative_end = [end_1[0] - end_2[0], end_1[1] - end_2[1], end_1[2] - end_2[2]];
+    intersect_sphere_lineseg(&[0.0, 0.0, 0.0], &(radius_1 + radius_2), &relative_start, &relative_end)
+}
+
+// change a team's score because of one of its drones
+pub(crate) fn award(scores: &mut HashMap<Team, i32>, events: &mut Vec<Event>, player: PlayerId, points: i32) {
+    if points != 0 {
+        *scores.get_mut(&player.team).unwrap() += points;
+        events.push(Event::ScoreChanged { player, points });
+    }
+}
+
+// take damage, shield first, returns whether it killed the drone
+fn hurt(
+    player: &mut Player,
+    events: &mut Vec<Event>,
+    attacker: Option<PlayerId>,
+    cause: DeathCause,
+    damage: f32
+) -> bool {
+    if damage <= 0.0 {
+        return false;
+    }
+    let absorbed = damage.min(player.shield);
+    player.shield -= absorbed;
+    player.health = (player.health - (damage - absorbed)).max(0.0);
+    player.damage_timer = 0;
+    events.push(Event::PlayerDamaged { victim: player.id(), attacker, cause, damage, absorbed });
+    if player.health > 0.0 {
+        return false;
+    }
+    player.is_dead = true;
+    player.respawn_timer = 0;
+    true
+}
+
+// a drone's weapon hitting a drone
+struct Hit {
+    shooter: PlayerId,
+    cause: DeathCause,
+    damage: f32,
+}
+
+// damage a drone with a weapon, scoring for the shooter's team if it kills
+fn weapon_hit(
+    player: &mut Player,
+    scores: &mut HashMap<Team, i32>,
+    events: &mut Vec<Event>,
+    scoring: &ScoringRules,
+    hit: Hit
+) {
+    let killed = hurt(player, events, Some(hit.shooter), hit.cause, hit.damage);
+    if hit.shooter.team == player.team {
+        events.push(Event::FriendlyFire {
+            victim: player.id(),
+            shooter: hit.shooter,
+        });
+    }
+    if !killed {
+        return;
+    }
+    if hit.shooter.team != player.team {
+        award(scores, events, hit.shooter, scoring.kill_reward);
+    } else {
+        award(scores, events, hit.shooter, -scoring.friendly_fire_penalty);
+    }
+    award(scores, events, player.id(), -scoring.death_penalty);
+    events.push(Event::PlayerKilled {
+        victim: player.id(),
+        killer: Some(hit.shooter),
+        cause: hit.cause,
+    });
+}
+
+// where something moving in a straight line from `start` to `end` during the tick was `t`
+// of the way through it
+fn position_at(start: &[f32;3], end: &[f32;3], t: f32) -> [f32;3] {
+    [
+        start[0] + (end[0] - start[0]) * t,
+        start[1] + (end[1] - start[1]) * t,
+        start[2] + (end[2] - start[2]) * t,
+    ]
+}
+
+// push a drone that hit an obstacle `t` of the way through the tick back out to the
+// obstacle's surface, on the side it hit, and bounce it off. Returns the impact speed
+fn bounce_off_obstacle(player: &mut Player, obstacle: &Obstacle, t: f32) -> f32 {
+    let normal = direction_vec3(
+        &position_at(&player.prev_position, &player.position, t),
+        &position_at(&obstacle.prev_position(), &obstacle.position, t),
+    );
+    let relative = [
+        player.velocity[0] - obstacle.velocity[0],
+        player.velocity[1] - obstacle.velocity[1],
+        player.velocity[2] - obstacle.velocity[2],
+    ];
+    let along_normal = dot_vec3(&relative, &normal);
+    if along_normal < 0.0 {
+        player.velocity = [
+            player.velocity[0] - 2.0 * along_normal * normal[0],
+            player.velocity[1] - 2.0 * along_normal * normal[1],
+            player.velocity[2] - 2.0 * along_normal * normal[2],
+        ];
+    }
+    let surface_distance = obstacle.radius + PLAYER_RADIUS + CONTACT_GAP;
+    player.position = [
+        obstacle.position[0] + normal[0] * surface_distance,
+        obstacle.position[1] + normal[1] * surface_distance,
+        obstacle.position[2] + normal[2] * surface_distance,
+    ];
+    length_vec3(&relative)
+}
+
+// push two drones that collided `t` of the way through the tick apart and bounce them off
+// each other like equally heavy balls. Returns the impact speed
+fn bounce_off_each_other(player_1: &mut Player, player_2: &mut Player, t: f32) -> f32 {
+    let normal = direction_vec3(
+        &position_at(&player_1.prev_position, &player_1.position, t),
+        &position_at(&player_2.prev_position, &player_2.position, t),
+    );
+    let relative = [
+        player_1.velocity[0] - player_2.velocity[0],
+        player_1.velocity[1] - player_2.velocity[1],
+        player_1.velocity[2] - player_2.velocity[2],
+    ];
+    let along_normal = dot_vec3(&relative, &normal);
+    if along_normal < 0.0 {
+        // swap the parts of their velocities along the normal
+        let exchanged = [along_normal * normal[0], along_normal * normal[1], along_normal * normal[2]];
+        player_1.velocity = [
+            player_1.velocity[0] - exchanged[0],
+            player_1.velocity[1] - exchanged[1],
+            player_1.velocity[2] - exchanged[2],
+        ];
+        add_vec3(&mut player_2.velocity, &exchanged);
+    }
+    let middle = [
+        (player_1.position[0] + player_2.position[0]) / 2.0,
+        (player_1.position[1] + player_2.position[1]) / 2.0,
+        (player_1.position[2] + player_2.position[2]) / 2.0,
+    ];
+    let half_distance = PLAYER_RADIUS + CONTACT_GAP / 2.0;
+    player_1.position = [
+        middle[0] + normal[0] * half_distance,
+        middle[1] + normal[1] * half_distance,
+        middle[2] + normal[2] * half_distance,
+    ];
+    player_2.position = [
+        middle[0] - normal[0] * half_distance,
+        middle[1] - normal[1] * half_distance,
+        middle[2] - normal[2] * half_distance,
+    ];
+    length_vec3(&relative)
+}
+
+// slow down by drag, then limit to the maximum speed, raised by `boost`
+fn apply_drag(velocity: &mut [f32;3], flight_model: &FlightModel, boost: f32) {
+    let speed = length_vec3(velocity);
+    let mut factor = (1.0 - flight_model.linear_drag - flight_model.quadratic_drag * speed).max(0.0);
+    if let Some(max_speed) = flight_model.max_speed {
+        let max_speed = max_speed * boost;
+        if speed * factor > max_speed {
+            factor = max_speed / speed;
+        }
+    }
+    velocity[0] *= factor;
+    velocity[1] *= factor;
+    velocity[2] *= factor;
+}
+
+// bounce a bullet off the surface of an obstacle, centered at `center`, at the point it hit
+fn reflect_bullet(velocity: &mut [f32;3], hit_point: &[f32;3], center: &[f32;3]) {
+    let normal = direction_vec3(hit_point, center);
+    let along_normal = dot_vec3(velocity, &normal);
+    *velocity = [
+        velocity[0] - 2.0 * along_normal * normal[0],
+        velocity[1] - 2.0 * along_normal * normal[1],
+        velocity[2] - 2.0 * along_normal * normal[2],
+    ];
+}
+
+// what a bullet ran into
+enum BulletHit {
+    Obstacle(u64),
+    Drone(usize), // index into the players
+}
+
+// Used for despawning obstacles/bullets: exact collision not necessary
+fn inside_obstacle_area(
+    position: &[f32;3]
+) -> bool {
+    position[0] >= -OBSTACLE_AREA_SIZE
+        && position[0] <= OBSTACLE_AREA_SIZE
+        && position[1] >= -OBSTACLE_AREA_SIZE
+        && position[1] <= OBSTACLE_AREA_SIZE
+        && position[2] >= -OBSTACLE_AREA_SIZE
+        && position[2] <= OBSTACLE_AREA_SIZE
+}
+
+pub(crate) fn inside_game_area(
+    position: &[f32;3],
+    radius: &f32,
+) -> bool {
+    (position[0] - radius) >= -GAME_AREA_SIZE
+        && (position[0] + radius) <= GAME_AREA_SIZE
+        && (position[1] - radius) >= -GAME_AREA_SIZE
+        && (position[1] + radius) <= GAME_AREA_SIZE
+        && (position[2] - radius) >= -GAME_AREA_SIZE
+        && (position[2] + radius) <= GAME_AREA_SIZE
+}
+
+// bounce off the walls of the arena, as if the drone had hit them partway through the tick
+fn bounce_off_walls(position: &mut [f32;3], velocity: &mut [f32;3], radius: &f32) {
+    let limit = GAME_AREA_SIZE - radius;
+    for (coordinate, speed) in position.iter_mut().zip(velocity.iter_mut()) {
+        if *coordinate > limit {
+            *coordinate = 2.0 * limit - *coordinate;
+            *speed = -*speed;
+        } else if *coordinate < -limit {
+            *coordinate = -2.0 * limit - *coordinate;
+            *speed = -*speed;
+        }
+    }
+}
+
+// leave through one wall, come back in through the opposite one
+fn wrap_around(position: &mut [f32;3]) {
+    for coordinate in position.iter_mut() {
+        if *coordinate > GAME_AREA_SIZE {
+            *coordinate -= 2.0 * GAME_AREA_SIZE;
+        } else if *coordinate < -GAME_AREA_SIZE {
+            *coordinate += 2.0 * GAME_AREA_SIZE;
+        }
+    }
+}
+
+// push back towards the center in proportion to how far into the margin the drone is
+fn push_from_walls(position: &[f32;3], velocity: &mut [f32;3], radius: &f32, strength: f32, margin: f32) {
+    let limit = GAME_AREA_SIZE - margin;
+    for (coordinate, speed) in position.iter().zip(velocity.iter_mut()) {
+        let overshoot = coordinate.abs() + radius - limit;
+        if overshoot > 0.0 {
+            *speed -= coordinate.signum() * strength * overshoot;
+        }
+    }
+}
+
+/// One of the competing teams, numbered from 0
+///
+/// Teams are named with letters like spreadsheet columns, A to Z then AA, AB and so on,
+/// and serialize as their name.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub struct Team(pub usize);
+
+impl Team {
+    pub const A: Team = Team(0);
+    pub const B: Team = Team(1);
+    /// The first `count` teams
+    pub fn all(count: usize) -> impl Iterator<Item = Team> {
+        (0..count).map(Team)
+    }
+}
+
+impl fmt::Display for Team {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut letters = vec![];
+        let mut n = self.0 + 1;
+        while n > 0 {
+            n -= 1;
+            letters.push((b'A' + (n % 26) as u8) as char);
+            n /= 26;
+        }
+        letters.iter().rev().try_for_each(|letter| write!(f, "{}", letter))
+    }
+}
+
+impl FromStr for Team {
+    type Err = String;
+    // case insensitive
+    fn from_str(name: &str) -> Result<Team, String> {
+        if name.is_empty() {
+            return Err(String::from("empty team name"));
+        }
+        let mut n: usize = 0;
+        for letter in name.chars() {
+            if !letter.is_ascii_alphabetic() {
+                return Err(format!("invalid team name: {}", name));
+            }
+            let value = (letter.to_ascii_uppercase() as u8 - b'A') as usize + 1;
+            n = n.checked_mul(26)
+                .and_then(|n| n.checked_add(value))
+                .ok_or_else(|| format!("invalid team name: {}", name))?;
+        }
+        Ok(Team(n - 1))
+    }
+}
+
+impl Serialize for Team {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Team {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Team, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A single drone's controls for one game tick, out-of-range values are clamped
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Controls {
+    pub rot_y: f32, // turn rate, positive is counterclockwise seen from above
+    #[serde(default)]
+    pub pitch: f32, // positive raises the nose
+    #[serde(default)]
+    pub roll: f32, // positive banks to the right
+    pub forward_back: f32,
+    pub up_down: f32,
+    #[serde(default)]
+    pub strafe: f32, // positive is left, only with strafe thrust in the flight model
+    pub shoot: bool,
+    pub shot_angle: f32, // radians, 0 is straight forward, -pi/2 straight down, pi/2 straight up
+    #[serde(default)]
+    pub weapon: usize, // index into the drone's loadout of the weapon to shoot
+}
+
+/// Input sent by a competitor each tick: controls for each of its drones, in order.
+/// Drones left without controls stay idle, extra controls are ignored
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InputRaw {
+    pub controls: Vec<Controls>,
+}
+
+impl Controls {
+    fn validated(&self) -> Controls {
+        Controls {
+            rot_y: self.rot_y.clamp(-1.0,1.0),
+            pitch: self.pitch.clamp(-1.0, 1.0),
+            roll: self.roll.clamp(-1.0, 1.0),
+            forward_back: self.forward_back.clamp(-1.0,1.0),
+            up_down: self.up_down.clamp(-1.0, 1.0),
+            strafe: self.strafe.clamp(-1.0, 1.0),
+            shoot: self.shoot,
+            shot_angle: self.shot_angle.clamp(-FRAC_PI_2, FRAC_PI_2),
+            weapon: self.weapon,
+        }
+    }
+    /// Controls of a drone that does nothing
+    pub fn empty() -> Controls {
+        Controls {
+            rot_y: 0.0,
+            pitch: 0.0,
+            roll: 0.0,
+            forward_back: 0.0,
+            up_down: 0.0,
+            strafe: 0.0,
+            shoot: false,
+            shot_angle: 0.0,
+            weapon: 0,
+        }
+    }
+    /// Parse a competitor's JSON input, invalid input leaves every drone idle
+    pub fn from_input(input: &str) -> Vec<Controls> {
+        match serde_json::from_str::<InputRaw>(input) {
+            Ok(raw) => raw.controls,
+            Err(_) => vec![],
+        }
+    }
+}
+
+/// Why a match ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndReason {
+    ScoreLimit,
+    /// A team completed the objective of the game mode
+    Objective,
+    MaxGameTicks,
+    #[serde(untagged)]
+    Event(TerminalEvent),
+}
+
+/// One of a drone's weapons, each with its own ammo and timers
+#[derive(Clone, Debug, Serialize)]
+pub struct Weapon {
+    pub kind: WeaponKind,
+    pub ammo: u32,
+    pub reload_timer: u32, // game ticks
+    pub fire_rate_timer: u32, // game ticks
+}
+
+impl Weapon {
+    fn new(kind: WeaponKind, config: &WeaponsConfig) -> Weapon {
+        Weapon {
+            kind,
+            ammo: config.ammo(kind).max,
+            reload_timer: 0,
+            fire_rate_timer: 0,
+        }
+    }
+    /// Whether enough time has passed since the last shot to fire again, `boost` is the
+    /// drone's fire rate boost
+    pub fn cooled_down(&self, config: &WeaponsConfig, boost: f32) -> bool {
+        self.fire_rate_timer as f32 * boost > config.ammo(self.kind).cooldown_ticks as f32
+    }
+}
+
+/// A drone
+#[derive(Clone, Debug, Serialize)]
+pub struct Player {
+    pub team: Team,
+    pub index: usize, // within the team
+    pub position: [f32;3],
+    pub prev_position: [f32;3], // where the drone was at the start of the tick
+    pub velocity: [f32;3],
+    pub rot_y: f32, // yaw in radians, 0 faces along x
+    pub pitch: f32, // radians, between -pi/2 (nose down) and pi/2 (nose up)
+    pub roll: f32, // radians, positive banks to the right
+    pub weapons: Vec<Weapon>, // the drone's loadout
+    pub selected_weapon: usize, // the weapon last picked by the controls
+    pub health: f32,
+    pub shield: f32,
+    pub damage_timer: u32, // game ticks since the drone last took damage
+    pub speed_boost: f32, // factor on thrust and top speed, 1 without a boost
+    pub speed_boost_timer: u32, // game ticks left
+    pub fire_rate_boost: f32, // factor on the rate of fire, 1 without a boost
+    pub fire_rate_boost_timer: u32, // game ticks left
+    pub is_dead: bool,
+    pub respawn_timer: u32, // game_ticks
+    pub stats: PlayerStats,
+}
+
+impl Player {
+    pub(crate) fn spawn(position: [f32;3], team: Team, index: usize, config: &MatchConfig) -> Player {
+        let loadout = config.weapons.loadout(PlayerId { team, index });
+        Player {
+            team,
+            index,
+            position,
+            prev_position: position,
+            velocity: [0.0,0.0,0.0],
+            rot_y: 0.0,
+            pitch: 0.0,
+            roll: 0.0,
+            weapons: loadout.iter().map(|&kind| Weapon::new(kind, &config.weapons)).collect(),
+            selected_weapon: 0,
+            health: config.health.max_health,
+            shield: config.health.max_shield,
+            damage_timer: 0,
+            speed_boost: 1.0,
+            speed_boost_timer: 0,
+            fire_rate_boost: 1.0,
+            fire_rate_boost_timer: 0,
+            is_dead: false,
+            respawn_timer: 0,
+            stats: PlayerStats::default(),
+        }
+    }
+    pub fn id(&self) -> PlayerId {
+        PlayerId {
+            team: self.team,
+            index: self.index,
+        }
+    }
+    /// Unit vectors pointing forward, left and up from the drone, following its yaw,
+    /// then its pitch, then its roll
+    pub fn axes(&self) -> [[f32;3];3] {
+        let (sin_yaw, cos_yaw) = self.rot_y.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        let (sin_roll, cos_roll) = self.roll.sin_cos();
+        let forward = [cos_pitch * cos_yaw, cos_pitch * sin_yaw, sin_pitch];
+        // left and up before rolling around the forward axis
+        let left = [-sin_yaw, cos_yaw, 0.0];
+        let up = [-sin_pitch * cos_yaw, -sin_pitch * sin_yaw, cos_pitch];
+        [
+            forward,
+            [
+                cos_roll * left[0] + sin_roll * up[0],
+                cos_roll * left[1] + sin_roll * up[1],
+                cos_roll * left[2] + sin_roll * up[2],
+            ],
+            [
+                cos_roll * up[0] - sin_roll * left[0],
+                cos_roll * up[1] - sin_roll * left[1],
+                cos_roll * up[2] - sin_roll * left[2],
+            ],
+        ]
+    }
+    /// The weapon last picked by the controls, `None` for drones without weapons
+    pub fn weapon(&self) -> Option<&Weapon> {
+        self.weapons.get(self.selected_weapon)
+    }
+    fn respawn(&mut self, position: [f32;3], config: &MatchConfig) {
+        self.position = position;
+        self.prev_position = position;
+        self.velocity = [0.0,0.0,0.0];
+        self.rot_y = 0.0;
+        self.pitch = 0.0;
+        self.roll = 0.0;
+        for weapon in self.weapons.iter_mut() {
+            *weapon = Weapon::new(weapon.kind, &config.weapons);
+        }
+        self.health = config.health.max_health;
+        self.shield = config.health.max_shield;
+        self.damage_timer = 0;
+        self.speed_boost = 1.0;
+        self.speed_boost_timer = 0;
+        self.fire_rate_boost = 1.0;
+        self.fire_rate_boost_timer = 0;
+        self.is_dead = false;
+        self.respawn_timer = 0;
+    }
+}
+
+/// An asteroid drifting through the arena, harmful to drones
+#[derive(Clone, Debug, Serialize)]
+pub struct Obstacle {
+    pub guid: u64,
+    pub position: [f32;3],
+    pub radius: f32,
+    pub velocity: [f32;3]
+}
+
+impl Obstacle {
+    // where the obstacle was at the start of the tick
+    fn prev_position(&self) -> [f32;3] {
+        [
+            self.position[0] - self.velocity[0],
+            self.position[1] - self.velocity[1],
+            self.position[2] - self.velocity[2],
+        ]
+    }
+}
+
+/// A bullet, treated as the line segment between its current and previous position
+#[derive(Clone, Debug, Serialize)]
+pub struct Bullet {
+    pub team: Team,
+    pub shooter: PlayerId,
+    pub guid: u64,
+    pub position: [f32;3],
+    pub prev_position: [f32;3],
+    pub velocity: [f32;3],
+    pub age: u32, // game ticks
+    pub distance_traveled: f32,
+}
+
+/// A homing missile, steering towards the closest living drone of another team
+#[derive(Clone, Debug, Serialize)]
+pub struct Missile {
+    pub team: Team,
+    pub shooter: PlayerId,
+    pub guid: u64,
+    pub position: [f32;3],
+    pub prev_position: [f32;3],
+    pub velocity: [f32;3],
+    /// The drone it's chasing, kept until it dies
+    pub target: Option<PlayerId>,
+    pub age: u32, // game ticks
+}
+
+/// A proximity mine, going off when a drone of another team comes close once armed
+#[derive(Clone, Debug, Serialize)]
+pub struct Mine {
+    pub team: Team,
+    pub owner: PlayerId,
+    pub guid: u64,
+    pub position: [f32;3],
+    pub age: u32, // game ticks
+    pub armed: bool,
+}
+
+/// An item waiting to be picked up, see [`crate::PickupConfig`]
+#[derive(Clone, Debug, Serialize)]
+pub struct Pickup {
+    pub guid: u64,
+    pub position: [f32;3],
+    pub radius: f32,
+    pub kind: PickupKind,
+}
+
+/// The complete state of a match
+///
+/// A match is fully determined by its config, its seed and the controls passed to
+/// each [`Gamestate::step`], so the same inputs always replay the same match.
+#[derive(Serialize)]
+pub struct Gamestate {
+    #[serde(skip)]
+    config: MatchConfig,
+    #[serde(skip)]
+    rng: StdRng,
+    seed: u64,
+    ticks_progressed: u32,
+    max_game_ticks: u32,
+    obstacles: HashMap<u64,Obstacle>,
+    obstacle_counter: u64,
+    // clients only get obstacles once they spawn
+    #[serde(skip)]
+    obstacle_spawner: ObstacleSpawner,
+    bullets: HashMap<u64,Bullet>,
+    bullet_counter: u64,
+    missiles: HashMap<u64,Missile>,
+    missile_counter: u64,
+    mines: HashMap<u64,Mine>,
+    mine_counter: u64,
+    pickups: HashMap<u64,Pickup>,
+    pickup_counter: u64,
+    // clients only get items once they spawn
+    #[serde(skip)]
+    pickup_spawner: PickupSpawner,
+    // every team's drones in turn, team A's first
+    players: Vec<Player>,
+    scores: HashMap<Team, i32>,
+    // rules of the game mode, whose state clients get as `objectives`
+    #[serde(skip)]
+    mode: Box<dyn GameMode>,
+    objectives: Objectives,
+    // everything that happened during the last tick
+    events: Vec<Event>,
+    // what each team can see, only tracked with fog of war
+    #[serde(skip)]
+    visibility: Option<HashMap<Team, Visibility>>
+}
+
+impl Gamestate {
+    // spawnpoint for a drone of `team` keeping clear of the drones at `drones`, and of all
+    // obstacles and bullets
+    fn find_fair_spawnpoint(&mut self, team: Team, drones: &[[f32;3]]) -> [f32;3] {
+        let obstacles: Vec<&Obstacle> = self.obstacles.values().collect();
+        let bullets: Vec<&Bullet> = self.bullets.values().collect();
+        let threats = Threats {
+            drones,
+            obstacles: &obstacles,
+            bullets: &bullets,
+        };
+        let spawning = &self.config.spawning;
+        spawn::fair_spawnpoint(spawning, spawning.zones.get(&team), &threats, &mut self.rng)
+    }
+    // where a team's drones start in `players`
+    fn team_offset(&self, team: Team) -> usize {
+        team.0 * self.team_size()
+    }
+    fn player_mut(&mut self, id: PlayerId) -> &mut Player {
+        let slot = self.team_offset(id.team) + id.index;
+        &mut self.players[slot]
+    }
+    /// Start a new match, all randomness in the match is drawn from `seed`
+    pub fn new(mut config: MatchConfig, seed: u64) -> Gamestate {
+        let mut rng = StdRng::seed_from_u64(seed);
+        config.teams = config.teams.max(1);
+        config.team_size = config.team_size.max(1);
+        let team_size = config.team_size;
+        let mode = modes::new(&config.mode, config.teams);
+        let objectives = mode.objectives();
+        let mut retval = Gamestate {
+            ticks_progressed: 0,
+            max_game_ticks: config.max_game_ticks,
+            obstacles: HashMap::new(),
+            obstacle_counter: 0,
+            obstacle_spawner: ObstacleSpawner::new(&config.obstacles, &mut rng),
+            bullets: HashMap::new(),
+            bullet_counter: 0,
+            missiles: HashMap::new(),
+            missile_counter: 0,
+            mines: HashMap::new(),
+            mine_counter: 0,
+            pickups: HashMap::new(),
+            pickup_counter: 0,
+            pickup_spawner: PickupSpawner::default(),
+            players: Team::all(config.teams)
+                .flat_map(|team| (0..team_size).map(move |index| (team, index)))
+                .map(|(team, index)| Player::spawn([0.0,0.0,0.0], team, index, &config))
+                .collect(),
+            scores: Team::all(config.teams).map(|team| (team, 0)).collect(),
+            mode,
+            objectives,
+            events: vec![],
+            visibility: None,
+            config,
+            rng,
+            seed,
+        };
+        // place drones one by one, each away from those already placed
+        let mut placed = vec![];
+        let ids: Vec<PlayerId> = retval.players.iter().map(Player::id).collect();
+        for id in ids {
+            let position = retval.find_fair_spawnpoint(id.team, &placed);
+            retval.player_mut(id).position = position;
+            placed.push(position);
+        }
+        retval.update_visibility();
+        retval
+    }
+    /// Play by the rules of `mode` instead of the game mode of the config, meant to be
+    /// called before the first tick
+    pub fn set_game_mode(&mut self, mode: Box<dyn GameMode>) {
+        self.objectives = mode.objectives();
+        self.mode = mode;
+    }
+    pub fn config(&self) -> &MatchConfig {
+        &self.config
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// Whether the match has ended, see [`Gamestate::end_reason`]
+    pub fn is_over(&self) -> bool {
+        self.end_reason().is_some()
+    }
+    /// Why the match ended: a configured terminal event, the game mode's objective or the
+    /// score limit during the last tick, or running out of ticks
+    pub fn end_reason(&self) -> Option<EndReason> {
+        for &event in &self.config.terminal_events {
+            let happened = match event {
+                TerminalEvent::AnyDeath => self.teams()
+                    .any(|team| self.deaths_last_tick(team) > 0),
+                TerminalEvent::TeamWipe => self.teams()
+                    .any(|team| self.alive_count(team) == 0),
+            };
+            if happened {
+                return Some(EndReason::Event(event));
+            }
+        }
+        if self.mode.is_over() {
+            return Some(EndReason::Objective);
+        }
+        if let Some(score_limit) = self.config.score_limit {
+            if self.teams().any(|team| self.score(team) >= score_limit) {
+                return Some(EndReason::ScoreLimit);
+            }
+        }
+        if self.ticks_progressed >= self.max_game_ticks {
+            return Some(EndReason::MaxGameTicks);
+        }
+        None
+    }
+    /// Outcome of the match so far, along with every drone's stats
+    pub fn result(&self) -> MatchResult {
+        MatchResult {
+            seed: self.seed,
+            ticks_progressed: self.ticks_progressed,
+            end_reason: self.end_reason(),
+            winner: self.winner(),
+            scores: self.scores.clone(),
+            players: self.players()
+                .iter()
+                .map(|player| PlayerResult {
+                    player: player.id(),
+                    stats: player.stats.clone(),
+                })
+                .collect(),
+        }
+    }
+    pub fn score(&self, team: Team) -> i32 {
+        self.scores[&team]
+    }
+    /// The team that won, or would win if the match ended now, as decided by the game
+    /// mode. `None` for a draw
+    pub fn winner(&self) -> Option<Team> {
+        self.mode.winner(&self.scores)
+    }
+    /// What the match is played for besides kills, see [`crate::GameModeConfig`]
+    pub fn objectives(&self) -> &Objectives {
+        &self.objectives
+    }
+    pub fn ticks_progressed(&self) -> u32 {
+        self.ticks_progressed
+    }
+    /// Every team in the match, in order
+    pub fn teams(&self) -> impl Iterator<Item = Team> {
+        Team::all(self.config.teams)
+    }
+    pub fn num_teams(&self) -> usize {
+        self.config.teams
+    }
+    /// All drones, ordered by team and then by index within the team
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+    /// A team's drones, ordered by index
+    pub fn team_players(&self, team: Team) -> &[Player] {
+        let offset = self.team_offset(team);
+        &self.players[offset..offset + self.team_size()]
+    }
+    /// Number of drones on each team
+    pub fn team_size(&self) -> usize {
+        self.config.team_size
+    }
+    /// What `team` can currently see, `None` unless fog of war is enabled
+    pub fn visibility(&self, team: Team) -> Option<&Visibility> {
+        self.visibility.as_ref().map(|visibility| &visibility[&team])
+    }
+    fn update_visibility(&mut self) {
+        let Some(fog_of_war) = &self.config.fog_of_war else {
+            return;
+        };
+        let obstacles: Vec<&Obstacle> = self.obstacles.values().collect();
+        let bullets: Vec<&Bullet> = self.bullets.values().collect();
+        let missiles: Vec<&Missile> = self.missiles.values().collect();
+        let mines: Vec<&Mine> = self.mines.values().collect();
+        let pickups: Vec<&Pickup> = self.pickups.values().collect();
+        let visibility = fog::compute_visibility(
+            fog_of_war,
+            self.config.teams,
+            &Scene {
+                players: &self.players,
+                obstacles: &obstacles,
+                bullets: &bullets,
+                missiles: &missiles,
+                mines: &mines,
+                pickups: &pickups,
+            },
+            self.ticks_progressed,
+            self.visibility.as_ref(),
+        );
+        self.visibility = Some(visibility);
+    }
+    pub fn obstacles(&self) -> impl Iterator<Item = &Obstacle> {
+        self.obstacles.values()
+    }
+    pub fn bullets(&self) -> impl Iterator<Item = &Bullet> {
+        self.bullets.values()
+    }
+    pub fn missiles(&self) -> impl Iterator<Item = &Missile> {
+        self.missiles.values()
+    }
+    pub fn mines(&self) -> impl Iterator<Item = &Mine> {
+        self.mines.values()
+    }
+    pub fn pickups(&self) -> impl Iterator<Item = &Pickup> {
+        self.pickups.values()
+    }
+    /// Events of the last computed tick, in the order they happened
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+    /// Number of a team's drones killed during the last computed tick
+    pub fn deaths_last_tick(&self, team: Team) -> usize {
+        self.events
+            .iter()
+            .filter(|event| matches!(event, Event::PlayerKilled { victim, .. } if victim.team == team))
+            .count()
+    }
+    pub fn alive_count(&self, team: Team) -> usize {
+        self.players()
+            .iter()
+            .filter(|player| player.team == team && !player.is_dead)
+            .count()
+    }
+    // each laser beam stops at the first drone or obstacle in its way
+    fn fire_lasers(&mut self, shots: &[(PlayerId, [f32;3], [f32;3])]) {
+        if shots.is_empty() {
+            return;
+        }
+        let laser = &self.config.weapons.laser;
+        // beams are instant, so everything is checked where it is now
+        let obstacle_grid = Grid::new(self.obstacles.values().map(|obstacle| {
+            (obstacle.guid, Aabb::swept(&obstacle.position, &obstacle.position, obstacle.radius))
+        }));
+        let player_grid = Grid::new(self.players.iter()
+            .enumerate()
+            .filter(|(_, player)| !player.is_dead)
+            .map(|(i, player)| (i, Aabb::swept(&player.position, &player.position, PLAYER_RADIUS))));
+        for &(shooter, from, direction) in shots {
+            let reach = [
+                from[0] + direction[0] * laser.range,
+                from[1] + direction[1] * laser.range,
+                from[2] + direction[2] * laser.range,
+            ];
+            let beam = Aabb::swept(&from, &reach, 0.0);
+            let blocked = obstacle_grid.query(&beam).into_iter()
+                .filter_map(|guid| {
+                    let obstacle = &self.obstacles[&guid];
+                    intersect_sphere_lineseg(&obstacle.position, &obstacle.radius, &from, &reach)
+                })
+                .fold(1.0, f32::min);
+            let hit = player_grid.query(&beam).into_iter()
+                .filter(|&i| !self.players[i].is_dead && self.players[i].id() != shooter)
+                .filter_map(|i| {
+                    intersect_sphere_lineseg(&self.players[i].position, &PLAYER_RADIUS, &from, &reach).map(|t| (t, i))
+                })
+                .filter(|(t, _)| *t < blocked)
+                .min_by(|(t1, _), (t2, _)| t1.total_cmp(t2));
+            let t = hit.map_or(blocked, |(t, _)| t);
+            self.events.push(Event::LaserFired {
+                shooter,
+                from,
+                to: [
+                    from[0] + (reach[0] - from[0]) * t,
+                    from[1] + (reach[1] - from[1]) * t,
+                    from[2] + (reach[2] - from[2]) * t,
+                ],
+            });
+            if let Some((_, i)) = hit {
+                let hit = Hit { shooter, cause: DeathCause::Laser, damage: laser.damage };
+                weapon_hit(&mut self.players[i], &mut self.scores, &mut self.events, &self.config.scoring, hit);
+            }
+        }
+    }
+    // each bullet is spent on the first drone or obstacle along its path, unless obstacles
+    // reflect it, then it carries on from where it hit for the rest of the tick. Returns
+    // the pushes obstacles get from the bullets they absorbed
+    fn collide_bullets(&mut self, obstacle_grid: &Grid<u64>) -> Vec<(u64, [f32;3])> {
+        let bullet_obstacle = self.config.bullet_obstacle;
+        let damage = self.config.health.bullet_damage;
+        let player_grid = Grid::new(self.players.iter()
+            .enumerate()
+            .filter(|(_, player)| !player.is_dead)
+            .map(|(i, player)| (i, Aabb::swept(&player.prev_position, &player.position, PLAYER_RADIUS))));
+        let mut absorbed = vec![];
+        let mut bullets_to_delete: Vec<u64> = vec![];
+        let mut guids: Vec<u64> = self.bullets.keys().copied().collect();
+        guids.sort_unstable();
+        for guid in guids {
+            let bullet = &self.bullets[&guid];
+            let (mut from, mut to) = (bullet.prev_position, bullet.position);
+            // how far through the tick the bullet is at `from`
+            let mut elapsed = 0.0;
+            let mut reflected_off = None;
+            for _ in 0..=MAX_BULLET_REFLECTIONS {
+                let path = Aabb::swept(&from, &to, 0.0);
+                let obstacles = if bullet_obstacle == BulletObstacle::Pass { vec![] } else { obstacle_grid.query(&path) };
+                let obstacle_hits = obstacles.into_iter()
+                    .filter(|&guid| reflected_off != Some(guid))
+                    .filter_map(|guid| {
+                        let obstacle = &self.obstacles[&guid];
+                        let start = position_at(&obstacle.prev_position(), &obstacle.position, elapsed);
+                        sweep_spheres(&from, &to, &0.0, &start, &obstacle.position, &obstacle.radius)
+                            .map(|t| (t, BulletHit::Obstacle(guid)))
+                    });
+                let drone_hits = player_grid.query(&path).into_iter()
+                    .filter(|&i| !self.players[i].is_dead)
+                    .filter_map(|i| {
+                        let player = &self.players[i];
+                        let start = position_at(&player.prev_position, &player.position, elapsed);
+                        sweep_spheres(&from, &to, &0.0, &start, &player.position, &PLAYER_RADIUS)
+                            .map(|t| (t, BulletHit::Drone(i)))
+                    });
+                let Some((t, hit)) = obstacle_hits.chain(drone_hits).min_by(|(t1, _), (t2, _)| t1.total_cmp(t2)) else {
+                    break;
+                };
+                let bullet = self.bullets.get_mut(&guid).unwrap();
+                let obstacle = match hit {
+                    BulletHit::Drone(i) => {
+                        bullets_to_delete.push(guid);
+                        let hit = Hit { shooter: bullet.shooter, cause: DeathCause::Bullet, damage };
+                        weapon_hit(&mut self.players[i], &mut self.scores, &mut self.events, &self.config.scoring, hit);
+                        break;
+                    },
+                    BulletHit::Obstacle(guid) => &self.obstacles[&guid],
+                };
+                self.events.push(Event::BulletHitObstacle { bullet: guid, obstacle: obstacle.guid });
+                if bullet_obstacle != BulletObstacle::Reflect {
+                    if bullet_obstacle == BulletObstacle::Absorb {
+                        let push = ABSORBED_MOMENTUM / obstacle.radius;
+                        absorbed.push((obstacle.guid, [bullet.velocity[0] * push, bullet.velocity[1] * push, bullet.velocity[2] * push]));
+                    }
+                    bullets_to_delete.push(guid);
+                    break;
+                }
+                let start = position_at(&obstacle.prev_position(), &obstacle.position, elapsed);
+                let center = position_at(&start, &obstacle.position, t);
+                let hit_point = position_at(&from, &to, t);
+                reflect_bullet(&mut bullet.velocity, &hit_point, &center);
+                elapsed += (1.0 - elapsed) * t;
+                from = hit_point;
+                to = [
+                    hit_point[0] + bullet.velocity[0] * (1.0 - elapsed),
+                    hit_point[1] + bullet.velocity[1] * (1.0 - elapsed),
+                    hit_point[2] + bullet.velocity[2] * (1.0 - elapsed),
+                ];
+                bullet.prev_position = from;
+                bullet.position = to;
+                reflected_off = Some(obstacle.guid);
+            }
+        }
+        for to_delete in bullets_to_delete {
+            self.bullets.remove(&to_delete);
+        }
+        absorbed
+    }
+    // steer missiles towards their targets and move them, they explode once out of time
+    fn move_missiles(&mut self) {
+        let config = &self.config.weapons.missile;
+        let mut guids: Vec<u64> = self.missiles.keys().copied().collect();
+        guids.sort_unstable();
+        for guid in guids {
+            let slot = self.missiles[&guid].target.map(|target| self.team_offset(target.team) + target.index);
+            let missile = self.missiles.get_mut(&guid).unwrap();
+            let target = slot
+                .map(|slot| &self.players[slot])
+                .filter(|target| !target.is_dead)
+                .or_else(|| weapons::closest_enemy(&self.players, missile.team, &missile.position));
+            missile.target = target.map(Player::id);
+            if let Some(target) = target {
+                missile.velocity = weapons::steer(&missile.velocity, &missile.position, &target.position, config.turn_rate);
+            }
+            missile.prev_position = missile.position;
+            add_vec3(&mut missile.position, &missile.velocity);
+            missile.age += 1;
+            if missile.age > config.lifetime {
+                self.events.push(Event::MissileExploded { shooter: missile.shooter, missile: guid });
+                self.missiles.remove(&guid);
+            } else if !inside_obstacle_area(&missile.position) {
+                self.missiles.remove(&guid);
+            }
+        }
+    }
+    // missiles explode on the first drone or obstacle they hit, but never hit their shooter
+    fn collide_missiles(&mut self, obstacle_grid: &Grid<u64>) {
+        let damage = self.config.weapons.missile.damage;
+        // drones may have been pushed around since the tick's earlier checks
+        let player_grid = Grid::new(self.players.iter()
+            .enumerate()
+            .filter(|(_, player)| !player.is_dead)
+            .map(|(i, player)| (i, Aabb::swept(&player.prev_position, &player.position, PLAYER_RADIUS))));
+        let mut guids: Vec<u64> = self.missiles.keys().copied().collect();
+        guids.sort_unstable();
+        for guid in guids {
+            let missile = &self.missiles[&guid];
+            let path = Aabb::swept(&missile.prev_position, &missile.position, 0.0);
+            let blocked = obstacle_grid.query(&path).into_iter()
+                .filter_map(|guid| {
+                    let obstacle = &self.obstacles[&guid];
+                    sweep_spheres(
+                        &missile.prev_position,
+                        &missile.position,
+                        &0.0,
+                        &obstacle.prev_position(),
+                        &obstacle.position,
+                        &obstacle.radius
+                    )
+                })
+                .min_by(f32::total_cmp);
+            let hit = player_grid.query(&path).into_iter()
+                .filter(|&i| !self.players[i].is_dead && self.players[i].id() != missile.shooter)
+                .filter_map(|i| {
+                    let player = &self.players[i];
+                    sweep_spheres(
+                        &missile.prev_position,
+                        &missile.position,
+                        &0.0,
+                        &player.prev_position,
+                        &player.position,
+                        &PLAYER_RADIUS
+                    ).map(|t| (t, i))
+                })
+                .filter(|(t, _)| blocked.is_none_or(|blocked| *t < blocked))
+                .min_by(|(t1, _), (t2, _)| t1.total_cmp(t2));
+            if blocked.is_none() && hit.is_none() {
+                continue;
+            }
+            let shooter = missile.shooter;
+            self.missiles.remove(&guid);
+            self.events.push(Event::MissileExploded { shooter, missile: guid });
+            if let Some((_, i)) = hit {
+                let hit = Hit { shooter, cause: DeathCause::Missile, damage };
+                weapon_hit(&mut self.players[i], &mut self.scores, &mut self.events, &self.config.scoring, hit);
+            }
+        }
+    }
+    // arm mines, and set off those a drone of another team came close to
+    fn trigger_mines(&mut self) {
+        let config = &self.config.weapons.mine;
+        let mut guids: Vec<u64> = self.mines.keys().copied().collect();
+        guids.sort_unstable();
+        for guid in guids {
+            let mine = self.mines.get_mut(&guid).unwrap();
+            mine.age += 1;
+            mine.armed = mine.age >= config.arm_ticks;
+            let triggered = mine.armed && self.players.iter().any(|player| {
+                !player.is_dead
+                    && player.team != mine.team
+                    && distance(&player.position, &mine.position) <= config.trigger_radius
+            });
+            if !triggered {
+                continue;
+            }
+            let mine = self.mines.remove(&guid).unwrap();
+            self.events.push(Event::MineExploded { owner: mine.owner, mine: guid });
+            for player in self.players.iter_mut() {
+                if player.is_dead || distance(&player.position, &mine.position) > config.blast_radius {
+                    continue;
+                }
+                let hit = Hit { shooter: mine.owner, cause: DeathCause::Mine, damage: config.damage };
+                weapon_hit(player, &mut self.scores, &mut self.events, &self.config.scoring, hit);
+            }
+        }
+    }
+    /// Advance the match by one tick using each team's JSON input, as sent over the
+    /// network, in team order
+    pub fn compute_next_tick(&mut self, inputs: &[impl AsRef<str>]) {
+        let controls: Vec<Vec<Controls>> = inputs.iter()
+            .map(|input| Controls::from_input(input.as_ref()))
+            .collect();
+        self.step(&controls);
+    }
+    /// Advance the match by one tick, given the controls of each team's drones indexed
+    /// `[team][drone]`. Drones without controls stay idle
+    pub fn step(&mut self, controls: &[Vec<Controls>]) {
+        let controls: Vec<Controls> = self.players.iter()
+            .map(|player| {
+                controls.get(player.team.0)
+                    .and_then(|team_controls| team_controls.get(player.index))
+                    .map_or(Controls::empty(), Controls::validated)
+            })
+            .collect();
+        self.events.clear();
+        // tick main game timer
+        self.ticks_progressed += 1;
+        // spawn, move, and despawn obstacles
+        let progress = self.ticks_progressed as f32 / self.max_game_ticks.max(1) as f32;
+        for spawn in self.obstacle_spawner.tick(&self.config.obstacles, progress, &mut self.rng) {
+            let spawned = Obstacle {
+                guid: self.obstacle_counter,
+                position: spawn.position,
+                velocity: spawn.velocity,
+                radius: spawn.radius
+            };
+            self.events.push(Event::ObstacleSpawned { obstacle: spawned.guid });
+            self.obstacles.insert(spawned.guid, spawned);
+            self.obstacle_counter += 1;
+        }
+        {
+            let mut obstacles_to_delete: Vec<u64> = vec![];
+            for kv in &mut self.obstacles.iter_mut() {
+                // add velocities to positions for obstacles
+                let obstacle = kv.1;
+                add_vec3(&mut obstacle.position, &obstacle.velocity);
+                // delete when out-of-bounds
+                if !inside_obstacle_area(&obstacle.position) {
+                    obstacles_to_delete.push(obstacle.guid);
+                }   
+            }
+            for to_delete in obstacles_to_delete {
+                self.obstacles.remove(&to_delete);
+            }
+        }
+        // spawn items
+        if let Some(pickup_config) = &self.config.pickups {
+            let items: Vec<&Pickup> = self.pickups.values().collect();
+            if let Some(spawn) = self.pickup_spawner.tick(pickup_config, &items, &mut self.rng) {
+                let spawned = Pickup {
+                    guid: self.pickup_counter,
+                    position: spawn.position,
+                    radius: pickup_config.radius,
+                    kind: spawn.kind,
+                };
+                self.events.push(Event::PickupSpawned { pickup: spawned.guid });
+                self.pickups.insert(spawned.guid, spawned);
+                self.pickup_counter += 1;
+            }
+        }
+        // respawn drones whose timer ran out, away from anything that could kill them
+        let respawning: Vec<PlayerId> = self.players.iter()
+            .filter(|player| player.is_dead && player.respawn_timer > MAX_PLAYER_RESPAWN_TIMER)
+            .map(Player::id)
+            .collect();
+        for id in respawning {
+            let drones: Vec<[f32;3]> = self.players.iter()
+                .filter(|player| !player.is_dead && player.id() != id)
+                .map(|player| player.position)
+                .collect();
+            let position = self.find_fair_spawnpoint(id.team, &drones);
+            let slot = self.team_offset(id.team) + id.index;
+            self.players[slot].respawn(position, &self.config);
+            self.events.push(Event::PlayerRespawned { player: id });
+        }
+        // laser shots this tick: shooter, where from and in which direction
+        let mut lasers: Vec<(PlayerId, [f32;3], [f32;3])> = vec![];
+        // player movement and shooting logic
+        for (player, &controls) in self.players.iter_mut().zip(&controls) {
+            // collisions are swept from here, however the drone gets pushed around later
+            player.prev_position = player.position;
+            if player.is_dead {
+                player.respawn_timer += 1;
+                continue; // player is dead, don't bother with other logic
+            }
+            let weapons = &self.config.weapons;
+            for weapon in player.weapons.iter_mut() {
+                let ammo = weapons.ammo(weapon.kind);
+                if weapon.fire_rate_timer <= ammo.cooldown_ticks {
+                    weapon.fire_rate_timer += 1;
+                }
+                if weapon.reload_timer <= ammo.reload_ticks {
+                    weapon.reload_timer += 1;
+                } else {
+                    if weapon.ammo < ammo.max {
+                        weapon.ammo += 1;
+                        weapon.reload_timer = 0;
+                    }
+                }
+            }
+            let health = &self.config.health;
+            if player.damage_timer <= health.shield_regen_delay {
+                player.damage_timer += 1;
+            } else if player.shield < health.max_shield {
+                player.shield = (player.shield + health.shield_regen).min(health.max_shield);
+            }
+            // boosts from items wear off
+            if player.speed_boost_timer > 0 {
+                player.speed_boost_timer -= 1;
+            } else {
+                player.speed_boost = 1.0;
+            }
+            if player.fire_rate_boost_timer > 0 {
+                player.fire_rate_boost_timer -= 1;
+            } else {
+                player.fire_rate_boost = 1.0;
+            }
+            let flight_model = &self.config.flight_model;
+            player.rot_y += controls.rot_y * flight_model.turn_rate;
+            player.pitch = (player.pitch + controls.pitch * flight_model.pitch_rate).clamp(-FRAC_PI_2, FRAC_PI_2);
+            player.roll = (player.roll + controls.roll * flight_model.roll_rate).rem_euclid(TAU);
+            // thrust along the drone's own axes
+            let [forward, left, up] = player.axes();
+            let thrusts = [
+                (forward, flight_model.thrust * player.speed_boost * controls.forward_back),
+                (left, flight_model.strafe_thrust * player.speed_boost * controls.strafe),
+                (up, flight_model.vertical_thrust * player.speed_boost * controls.up_down),
+            ];
+            for (axis, thrust) in thrusts {
+                player.velocity[0] += axis[0] * thrust;
+                player.velocity[1] += axis[1] * thrust;
+                player.velocity[2] += axis[2] * thrust;
+            }
+            apply_drag(&mut player.velocity, flight_model, player.speed_boost);
+            add_vec3(&mut player.position, &player.velocity);
+            player.stats.distance_traveled += length_vec3(&player.velocity);
+            player.stats.ticks_alive += 1;
+            if controls.weapon < player.weapons.len() {
+                player.selected_weapon = controls.weapon;
+            }
+            let id = player.id();
+            let boost = player.fire_rate_boost;
+            let Some(weapon) = player.weapons.get_mut(controls.weapon) else {
+                continue;
+            };
+            if !controls.shoot || weapon.ammo == 0 || !weapon.cooled_down(weapons, boost) {
+                continue;
+            }
+            weapon.fire_rate_timer = 0;
+            weapon.ammo -= 1;
+            // unit vector along the drone's nose, tilted towards its top by the shot angle
+            let (sin_shot, cos_shot) = controls.shot_angle.sin_cos();
+            let shot_direction = [
+                forward[0] * cos_shot + up[0] * sin_shot,
+                forward[1] * cos_shot + up[1] * sin_shot,
+                forward[2] * cos_shot + up[2] * sin_shot
+            ];
+            // projectiles start just clear of the drone
+            let muzzle = [
+                player.position[0] + PLAYER_RADIUS * 1.5 * shot_direction[0],
+                player.position[1] + PLAYER_RADIUS * 1.5 * shot_direction[1],
+                player.position[2] + PLAYER_RADIUS * 1.5 * shot_direction[2]
+            ];
+            match weapon.kind {
+                WeaponKind::Gun => {
+                    let bullet_speed = self.config.bullets.speed;
+                    let spawned = Bullet {
+                        guid: self.bullet_counter,
+                        team: player.team,
+                        shooter: id,
+                        position: muzzle,
+                        prev_position: muzzle,
+                        velocity: [
+                            shot_direction[0] * bullet_speed,
+                            shot_direction[1] * bullet_speed,
+                            shot_direction[2] * bullet_speed,
+                        ],
+                        age: 0,
+                        distance_traveled: 0.0,
+                    };
+                    self.events.push(Event::BulletFired { shooter: id, bullet: spawned.guid });
+                    self.bullets.insert(spawned.guid, spawned);
+                    self.bullet_counter += 1;
+                },
+                WeaponKind::Laser => lasers.push((id, player.position, shot_direction)),
+                WeaponKind::Missile => {
+                    let speed = weapons.missile.speed;
+                    let spawned = Missile {
+                        guid: self.missile_counter,
+                        team: player.team,
+                        shooter: id,
+                        position: muzzle,
+                        prev_position: muzzle,
+                        velocity: [
+                            shot_direction[0] * speed,
+                            shot_direction[1] * speed,
+                            shot_direction[2] * speed,
+                        ],
+                        target: None,
+                        age: 0,
+                    };
+                    self.events.push(Event::MissileFired { shooter: id, missile: spawned.guid });
+                    self.missiles.insert(spawned.guid, spawned);
+                    self.missile_counter += 1;
+                },
+                WeaponKind::Mine => {
+                    // dropped behind the drone, whichever way it's aiming
+                    let spawned = Mine {
+                        guid: self.mine_counter,
+                        team: player.team,
+                        owner: id,
+                        position: [
+                            player.position[0] - PLAYER_RADIUS * 1.5 * forward[0],
+                            player.position[1] - PLAYER_RADIUS * 1.5 * forward[1],
+                            player.position[2] - PLAYER_RADIUS * 1.5 * forward[2],
+                        ],
+                        age: 0,
+                        armed: false,
+                    };
+                    self.events.push(Event::MineLaid { owner: id, mine: spawned.guid });
+                    self.mines.insert(spawned.guid, spawned);
+                    self.mine_counter += 1;
+                    // the drone's oldest mines make way for the new one
+                    let mut own_mines: Vec<u64> = self.mines.values()
+                        .filter(|mine| mine.owner == id)
+                        .map(|mine| mine.guid)
+                        .collect();
+                    own_mines.sort_unstable();
+                    let excess = own_mines.len().saturating_sub(weapons.mine.max_per_drone);
+                    for guid in &own_mines[..excess] {
+                        self.mines.remove(guid);
+                    }
+                },
+            }
+        }
+        self.fire_lasers(&lasers);
+        // move and despawn bullets
+        {
+            let bullets = &self.config.bullets;
+            let mut bullets_to_delete: Vec<u64> = vec![];
+            for kv in &mut self.bullets.iter_mut() {
+                // add velocities to positions for bullets
+                let bullet = kv.1;
+                bullet.velocity[2] -= bullets.gravity;
+                bullet.prev_position = bullet.position;
+                add_vec3(&mut bullet.position, &bullet.velocity);
+                bullet.age += 1;
+                bullet.distance_traveled += length_vec3(&bullet.velocity);
+                // delete when out-of-bounds or spent
+                if !inside_obstacle_area(&bullet.position)
+                || bullets.max_range.is_some_and(|max_range| bullet.distance_traveled > max_range)
+                || bullets.max_lifetime.is_some_and(|max_lifetime| bullet.age > max_lifetime) {
+                    bullets_to_delete.push(bullet.guid);
+                }
+            }
+            for to_delete in bullets_to_delete {
+                self.bullets.remove(&to_delete);
+            }
+        }
+        self.move_missiles();
+        // only things sharing a grid cell are checked for collisions
+        let obstacle_grid = Grid::new(self.obstacles.values().map(|obstacle| {
+            (obstacle.guid, Aabb::swept(&obstacle.prev_position(), &obstacle.position, obstacle.radius))
+        }));
+        let scoring = &self.config.scoring;
+        let health = &self.config.health;
+        // collide players with each other
+        let player_paths: Vec<(usize, Aabb)> = self.players.iter()
+            .enumerate()
+            .filter(|(_, player)| !player.is_dead)
+            .map(|(i, player)| (i, Aabb::swept(&player.prev_position, &player.position, PLAYER_RADIUS)))
+            .collect();
+        let player_grid = Grid::new(player_paths.iter().copied());
+        let mut pairs: Vec<(usize, usize)> = vec![];
+        for (i, path) in &player_paths {
+            pairs.extend(player_grid.query(path).into_iter().filter(|j| j > i).map(|j| (*i, j)));
+        }
+        for (i, j) in pairs {
+            let (before, after) = self.players.split_at_mut(j);
+            let (player_1, player_2) = (&mut before[i], &mut after[0]);
+
+            if player_1.is_dead || player_2.is_dead {
+                continue;
+            }
+            let Some(t) = sweep_spheres(
+                &player_1.prev_position,
+                &player_1.position,
+                &PLAYER_RADIUS,
+                &player_2.prev_position,
+                &player_2.position,
+                &PLAYER_RADIUS,
+            ) else {
+                continue;
+            };
+            let damage = health.collision_damage * bounce_off_each_other(player_1, player_2, t);
+            let penalty = if player_1.team == player_2.team {
+                scoring.teammate_collision_penalty
+            } else {
+                scoring.enemy_collision_penalty
+            };
+            // each drone is hurt by, and credited with killing, the other
+            let (id_1, id_2) = (player_1.id(), player_2.id());
+            for (victim, attacker) in [(player_1, id_2), (player_2, id_1)] {
+                if hurt(victim, &mut self.events, Some(attacker), DeathCause::Collision, damage) {
+                    award(&mut self.scores, &mut self.events, victim.id(), -penalty);
+                    self.events.push(Event::PlayerKilled {
+                        victim: victim.id(),
+                        killer: Some(attacker),
+                        cause: DeathCause::Collision,
+                    });
+                }
+            }
+        }
+        // collide players with obstacles
+        for player in self.players.iter_mut() {
+            let path = Aabb::swept(&player.prev_position, &player.position, PLAYER_RADIUS);
+            for guid in obstacle_grid.query(&path) {
+                let obstacle = &self.obstacles[&guid];
+                if player.is_dead {
+                    break;
+                }
+                let Some(t) = sweep_spheres(
+                    &player.prev_position,
+                    &player.position,
+                    &PLAYER_RADIUS,
+                    &obstacle.prev_position(),
+                    &obstacle.position,
+                    &obstacle.radius
+                ) else {
+                    continue;
+                };
+                let damage = health.obstacle_damage * bounce_off_obstacle(player, obstacle, t);
+                if hurt(player, &mut self.events, None, DeathCause::Obstacle, damage) {
+                    award(&mut self.scores, &mut self.events, player.id(), -scoring.obstacle_death_penalty);
+                    self.events.push(Event::PlayerKilled {
+                        victim: player.id(),
+                        killer: None,
+                        cause: DeathCause::Obstacle,
+                    });
+                }
+            }
+        }
+        let absorbed = self.collide_bullets(&obstacle_grid);
+        let scoring = &self.config.scoring;
+        // apply the boundary policy to players that are out-of-bounds
+        for player in self.players.iter_mut() {
+            if player.is_dead {
+                continue;
+            }
+            // the force field acts before drones reach the walls
+            if let BoundaryPolicy::ForceField { strength, margin } = self.config.boundary {
+                push_from_walls(&player.position, &mut player.velocity, &PLAYER_RADIUS, strength, margin);
+            }
+            // wrapping drones are only out once their center crosses the wall
+            let radius = if self.config.boundary == BoundaryPolicy::Wrap { 0.0 } else { PLAYER_RADIUS };
+            if inside_game_area(&player.position, &radius) {
+                continue;
+            }
+            self.events.push(Event::OutOfBounds { player: player.id() });
+            match self.config.boundary {
+                BoundaryPolicy::Kill => {
+                    player.health = 0.0;
+                    player.shield = 0.0;
+                    player.is_dead = true;
+                    player.respawn_timer = 0;
+                    award(&mut self.scores, &mut self.events, player.id(), -scoring.out_of_bounds_penalty);
+                    self.events.push(Event::PlayerKilled {
+                        victim: player.id(),
+                        killer: None,
+                        cause: DeathCause::OutOfBounds,
+                    });
+                },
+                BoundaryPolicy::Bounce => bounce_off_walls(&mut player.position, &mut player.velocity, &PLAYER_RADIUS),
+                BoundaryPolicy::Wrap => {
+                    let exit = player.position;
+                    wrap_around(&mut player.position);
+                    // later sweeps follow the drone in through the opposite wall
+                    let shift = [
+                        player.position[0] - exit[0],
+                        player.position[1] - exit[1],
+                        player.position[2] - exit[2],
+                    ];
+                    add_vec3(&mut player.prev_position, &shift);
+                },
+                BoundaryPolicy::ForceField { .. } => {},
+            }
+        }
+        self.collide_missiles(&obstacle_grid);
+        // only now that every collision of the tick is settled, so that the paths they
+        // were checked along stay put
+        for (guid, push) in absorbed {
+            if let Some(obstacle) = self.obstacles.get_mut(&guid) {
+                add_vec3(&mut obstacle.velocity, &push);
+            }
+        }
+        self.trigger_mines();
+        // hand out items to the drones that reached them
+        let mut guids: Vec<u64> = self.pickups.keys().copied().collect();
+        guids.sort_unstable();
+        for guid in guids {
+            let Some(i) = pickups::collector(&self.pickups[&guid], &self.players) else {
+                continue;
+            };
+            let item = self.pickups.remove(&guid).unwrap();
+            let player = &mut self.players[i];
+            pickups::apply(item.kind, player, &self.config);
+            self.events.push(Event::PickupCollected { player: player.id(), pickup: guid, kind: item.kind });
+        }
+        // the game mode hears about the tick's kills and items, then plays its part
+        let kills: Vec<(PlayerId, Option<PlayerId>)> = self.events.iter()
+            .filter_map(|event| match *event {
+                Event::PlayerKilled { victim, killer, .. } => Some((victim, killer)),
+                _ => None,
+            })
+            .collect();
+        let collected: Vec<(PlayerId, PickupKind)> = self.events.iter()
+            .filter_map(|event| match *event {
+                Event::PickupCollected { player, kind, .. } => Some((player, kind)),
+                _ => None,
+            })
+            .collect();
+        let mut arena = Arena {
+            players: &self.players,
+            scores: &mut self.scores,
+            events: &mut self.events,
+        };
+        for (victim, killer) in kills {
+            self.mode.on_kill(victim, killer, &mut arena);
+        }
+        for (player, kind) in collected {
+            self.mode.on_pickup(player, kind, &mut arena);
+        }
+        self.mode.on_tick(&mut arena);
+        self.objectives = self.mode.objectives();
+        for player in self.players.iter_mut() {
+            for event in &self.events {
+                player.stats.record(player.id(), event);
+            }
+        }
+        self.update_visibility();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ObstacleConfig, PickupConfig, ScoringRules, SpawnPattern, WeaponKind, WeaponsConfig};
+
+    fn assert_close(actual: Option<f32>, expected: f32) {
+        let actual = actual.expect("expected a hit");
+        assert!((actual - expected).abs() < 1e-5, "hit at {actual}, expected {expected}");
+    }
+
+    #[test]
+    fn segment_through_sphere_hits_where_it_enters() {
+        let t = intersect_sphere_lineseg(&[0.0, 0.0, 0.0], &1.0, &[-5.0, 0.0, 0.0], &[5.0, 0.0, 0.0]);
+        assert_close(t, 0.4);
+    }
+
+    #[test]
+    fn segment_beside_sphere_misses() {
+        let t = intersect_sphere_lineseg(&[0.0, 0.0, 0.0], &1.0, &[-5.0, 2.0, 0.0], &[5.0, 2.0, 0.0]);
+        assert_eq!(t, None);
+    }
+
+    #[test]
+    fn segment_grazing_sphere_hits_where_it_touches() {
+        let t = intersect_sphere_lineseg(&[0.0, 0.0, 0.0], &1.0, &[-5.0, 1.0, 0.0], &[5.0, 1.0, 0.0]);
+        assert_close(t, 0.5);
+    }
+
+    #[test]
+    fn segment_starting_inside_hits_at_its_start() {
+        let t = intersect_sphere_lineseg(&[0.0, 0.0, 0.0], &1.0, &[0.5, 0.0, 0.0], &[5.0, 0.0, 0.0]);
+        assert_eq!(t, Some(0.0));
+    }
+
+    #[test]
+    fn zero_length_segment_hits_only_inside() {
+        let inside = intersect_sphere_lineseg(&[0.0, 0.0, 0.0], &1.0, &[0.0, 0.5, 0.0], &[0.0, 0.5, 0.0]);
+        assert_eq!(inside, Some(0.0));
+        let outside = intersect_sphere_lineseg(&[0.0, 0.0, 0.0], &1.0, &[0.0, 3.0, 0.0], &[0.0, 3.0, 0.0]);
+        assert_eq!(outside, None);
+    }
+
+    #[test]
+    fn segment_ending_on_sphere_hits_at_its_end() {
+        let t = intersect_sphere_lineseg(&[0.0, 0.0, 0.0], &1.0, &[-5.0, 0.0, 0.0], &[-1.0, 0.0, 0.0]);
+        assert_close(t, 1.0);
+        let short = intersect_sphere_lineseg(&[0.0, 0.0, 0.0], &1.0, &[-5.0, 0.0, 0.0], &[-1.1, 0.0, 0.0]);
+        assert_eq!(short, None);
+    }
+
+    #[test]
+    fn spheres_passing_through_each_other_within_a_tick_collide() {
+        // apart at the start and at the end of the tick, they only meet halfway
+        let t = sweep_spheres(
+            &[-10.0, 0.0, 0.0],
+            &[10.0, 0.0, 0.0],
+            &PLAYER_RADIUS,
+            &[10.0, 0.0, 0.0],
+            &[-10.0, 0.0, 0.0],
+            &PLAYER_RADIUS,
+        );
+        assert_close(t, 0.45);
+        // a bullet skipping over a drone in one step
+        let t = sweep_spheres(
+            &[-3.0, 0.5, 0.0],
+            &[3.0, 0.5, 0.0],
+            &0.0,
+            &[0.0, 0.0, 0.0],
+            &[0.0, 0.0, 0.0],
+            &PLAYER_RADIUS,
+        );
+        assert!(t.is_some_and(|t| t > 0.0 && t < 0.5));
+    }
+
+    #[test]
+    fn spheres_moving_apart_do_not_collide() {
+        let t = sweep_spheres(
+            &[-3.0, 0.0, 0.0],
+            &[-10.0, 0.0, 0.0],
+            &PLAYER_RADIUS,
+            &[3.0, 0.0, 0.0],
+            &[10.0, 0.0, 0.0],
+            &PLAYER_RADIUS,
+        );
+        assert_eq!(t, None);
+    }
+
+    // a match with a single drone, placed by hand, and nothing else in the arena
+    fn lone_drone(boundary: BoundaryPolicy, position: [f32;3], velocity: [f32;3]) -> Gamestate {
+        let config = MatchConfig {
+            teams: 1,
+            team_size: 1,
+            boundary,
+            obstacles: ObstacleConfig {
+                pattern: SpawnPattern::Waves { interval: u32::MAX, count: 0 },
+                ..ObstacleConfig::default()
+            },
+            scoring: ScoringRules { out_of_bounds_penalty: 3, ..ScoringRules::default() },
+            ..MatchConfig::default()
+        };
+        let mut gamestate = Gamestate::new(config, 0);
+        gamestate.players[0].position = position;
+        gamestate.players[0].velocity = velocity;
+        gamestate
+    }
+
+    fn drone(gamestate: &Gamestate) -> &Player {
+        &gamestate.players[0]
+    }
+
+    fn idle(gamestate: &mut Gamestate) {
+        gamestate.step(&[]);
+    }
+
+    #[test]
+    fn kill_spares_drones_inside_the_arena() {
+        let mut gamestate = lone_drone(BoundaryPolicy::Kill, [0.0, 0.0, 0.0], [0.0, 0.0, 0.0]);
+        for _ in 0..50 {
+            idle(&mut gamestate);
+        }
+        assert!(!drone(&gamestate).is_dead);
+        assert!(!gamestate.events.iter().any(|event| matches!(event, Event::OutOfBounds { .. })));
+        assert_eq!(gamestate.score(Team::A), 0);
+    }
+
+    #[test]
+    fn kill_kills_drones_leaving_the_arena() {
+        let mut gamestate = lone_drone(BoundaryPolicy::Kill, [19.0, 0.0, 0.0], [0.2, 0.0, 0.0]);
+        idle(&mut gamestate);
+        assert!(drone(&gamestate).is_dead);
+        assert_eq!((drone(&gamestate).health, drone(&gamestate).shield), (0.0, 0.0));
+        assert!(gamestate.events.iter().any(|event| {
+            matches!(event, Event::PlayerKilled { cause: DeathCause::OutOfBounds, .. })
+        }));
+        assert_eq!(gamestate.score(Team::A), -3);
+    }
+
+    #[test]
+    fn bounce_reflects_drones_off_the_wall() {
+        let mut gamestate = lone_drone(BoundaryPolicy::Bounce, [18.9, 0.0, 0.0], [0.2, 0.0, 0.0]);
+        idle(&mut gamestate);
+        let player = drone(&gamestate);
+        assert!(!player.is_dead);
+        assert!(player.position[0] < GAME_AREA_SIZE - PLAYER_RADIUS);
+        assert!(player.velocity[0] < 0.0);
+        assert_eq!(gamestate.score(Team::A), 0);
+    }
+
+    #[test]
+    fn wrap_brings_drones_back_through_the_opposite_wall() {
+        let mut gamestate = lone_drone(BoundaryPolicy::Wrap, [19.9, 0.0, 0.0], [0.2, 0.0, 0.0]);
+        idle(&mut gamestate);
+        let player = drone(&gamestate);
+        assert!(!player.is_dead);
+        assert!(player.position[0] < -GAME_AREA_SIZE + 1.0);
+        assert!(player.velocity[0] > 0.0);
+        // it came in through that wall, rather than crossing the whole arena
+        assert!(player.prev_position[0] < -GAME_AREA_SIZE);
+    }
+
+    #[test]
+    fn force_field_pushes_drones_back_inside_its_margin() {
+        let boundary = BoundaryPolicy::ForceField { strength: 0.05, margin: 5.0 };
+        let mut gamestate = lone_drone(boundary.clone(), [17.0, 0.0, 0.0], [0.0, 0.0, 0.0]);
+        idle(&mut gamestate);
+        assert!(drone(&gamestate).velocity[0] < 0.0);
+        // drones clear of the margin are left alone
+        let mut gamestate = lone_drone(boundary, [0.0, 0.0, 0.0], [0.0, 0.0, 0.0]);
+        idle(&mut gamestate);
+        assert_eq!(drone(&gamestate).velocity, [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn reflected_bullets_carry_on_to_hit_drones_the_same_tick() {
+        let mut gamestate = lone_drone(BoundaryPolicy::Kill, [0.0, 0.0, 0.0], [0.0, 0.0, 0.0]);
+        gamestate.config.bullet_obstacle = BulletObstacle::Reflect;
+        gamestate.obstacles.insert(1, Obstacle { guid: 1, position: [5.0, 0.0, 0.0], radius: 1.0, velocity: [0.0, 0.0, 0.0] });
+        let shooter = PlayerId { team: Team::B, index: 0 };
+        gamestate.bullets.insert(2, Bullet {
+            team: Team::B,
+            shooter,
+            guid: 2,
+            position: [2.5, 0.0, 0.0],
+            prev_position: [2.5, 0.0, 0.0],
+            velocity: [6.0, 0.0, 0.0],
+            age: 0,
+            distance_traveled: 0.0,
+        });
+        let health = drone(&gamestate).health;
+        idle(&mut gamestate);
+        // off the obstacle at x = 4 and straight back through the drone
+        assert!(gamestate.events.iter().any(|event| matches!(event, Event::BulletHitObstacle { bullet: 2, obstacle: 1 })));
+        assert!(drone(&gamestate).health < health);
+        assert!(gamestate.bullets.is_empty());
+    }
+
+    #[test]
+    fn the_same_seed_and_controls_replay_the_same_match() {
+        let config = MatchConfig {
+            max_game_ticks: 600,
+            team_size: 4,
+            obstacles: ObstacleConfig {
+                pattern: SpawnPattern::Stream { interval: 2 },
+                min_radius: 4.0,
+                max_radius: 8.0,
+                ..ObstacleConfig::default()
+            },
+            bullet_obstacle: BulletObstacle::Absorb,
+            pickups: Some(PickupConfig { interval: 20, ..PickupConfig::default() }),
+            weapons: WeaponsConfig {
+                loadout: vec![WeaponKind::Gun, WeaponKind::Laser, WeaponKind::Missile, WeaponKind::Mine],
+                ..WeaponsConfig::default()
+            },
+            ..MatchConfig::default()
+        };
+        let mut runs = [Gamestate::new(config.clone(), 7), Gamestate::new(config, 7)];
+        for tick in 0..600 {
+            // every drone circles and fires, switching weapons now and then
+            let controls: Vec<Vec<Controls>> = (0..2)
+                .map(|team| (0..4).map(|index| Controls {
+                    rot_y: 0.02 * (index as f32 + 1.0),
+                    forward_back: 1.0,
+                    up_down: if team == 0 { 0.3 } else { -0.3 },
+                    shoot: true,
+                    weapon: (tick / 50 + index) % 4,
+                    ..Controls::empty()
+                }).collect())
+                .collect();
+            for gamestate in runs.iter_mut() {
+                gamestate.step(&controls);
+            }
+            let [first, second] = &runs;
+            assert_eq!(format!("{:?}", first.events), format!("{:?}", second.events), "tick {tick}");
+        }
+        let [first, second] = &runs;
+        assert_eq!(serde_json::to_value(first).unwrap(), serde_json::to_value(second).unwrap());
+    }
+}
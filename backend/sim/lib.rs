@@ -0,0 +1,42 @@
+//! Drones3D simulation
+//!
+//! The game itself, independent of any networking: build a [`Gamestate`] from a
+//! [`MatchConfig`] and a seed, advance it with [`Gamestate::step`] and read the
+//! resulting state back. The `server` binary serves this over TCP, while [`VecEnv`]
+//! steps many matches in parallel in-process for training.
+
+mod broadphase;
+pub mod config;
+pub mod events;
+pub mod fog;
+pub mod gamestate;
+pub mod modes;
+pub mod observation;
+mod obstacles;
+mod pickups;
+pub mod replay;
+pub mod reward;
+mod spawn;
+pub mod stats;
+mod vec3;
+pub mod vec_env;
+mod weapons;
+
+pub use config::{
+    AmmoConfig, BoundaryPolicy, BulletConfig, BulletObstacle, CaptureTheFlagConfig, FlightModel,
+    GameModeConfig, GameModeKind, GunConfig, HealthConfig, KingOfTheHillConfig, LaserConfig,
+    MatchConfig, MineConfig, MissileConfig, ObstacleConfig, PickupConfig, PickupKind, ScoringRules,
+    SpawnConfig, SpawnPattern, SpawnZone, TerminalEvent, WeaponKind, WeaponsConfig,
+};
+pub use events::{DeathCause, Event, PlayerId};
+pub use fog::FogOfWarConfig;
+pub use modes::{Arena, Flag, GameMode, Hill, Objectives};
+pub use gamestate::{
+    Bullet, Controls, EndReason, Gamestate, InputRaw, Mine, Missile, Obstacle, Pickup, Player, Team,
+    Weapon,
+};
+pub use observation::{FlatLayout, TeamObservation};
+pub use replay::ReplayWriter;
+pub use reward::{RewardConfig, RewardFunction, Rewards, ShapedReward};
+pub use stats::{DeathCounts, MatchResult, PlayerResult, PlayerStats};
+pub use vec_env::{EnvControls, GameModeFactory, VecEnv, VecStep};
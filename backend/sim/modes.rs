@@ -0,0 +1,494 @@
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+use serde::Serialize;
+use crate::config::{CaptureTheFlagConfig, GameModeConfig, KingOfTheHillConfig, PickupKind};
+use crate::events::{Event, PlayerId};
+use crate::gamestate::{award, inside_game_area, Player, Team};
+use crate::spawn::SPAWN_AREA_SIZE;
+use crate::vec3::distance;
+
+// Game modes decide what a match is played for. The scoring rules apply in every mode and
+// modes add their own objectives on top: they hear about every kill and collected item,
+// get a turn at the end of every tick, and decide when the match is won and by whom.
+// Matches play the built-in mode their config asks for, training code can also bring its
+// own by implementing GameMode and installing it with Gamestate::set_game_mode.
+
+/// What a game mode gets to see and change of the match
+pub struct Arena<'a> {
+    /// Every team's drones in turn, like [`crate::Gamestate::players`]
+    pub players: &'a [Player],
+    pub scores: &'a mut HashMap<Team, i32>,
+    pub events: &'a mut Vec<Event>,
+}
+
+impl Arena<'_> {
+    pub fn player(&self, id: PlayerId) -> &Player {
+        self.players.iter().find(|player| player.id() == id).unwrap()
+    }
+    /// Give points to `player`'s team, credited to `player`
+    pub fn award(&mut self, player: PlayerId, points: i32) {
+        award(self.scores, self.events, player, points);
+    }
+}
+
+/// Rules of a game mode, hooked into every tick of the match
+pub trait GameMode: Send {
+    /// At the end of every tick, once the tick's kills and items have been handled
+    fn on_tick(&mut self, _arena: &mut Arena) {}
+    /// `victim` died this tick, `killer` is the drone credited with it, if any
+    fn on_kill(&mut self, _victim: PlayerId, _killer: Option<PlayerId>, _arena: &mut Arena) {}
+    /// `player` collected an item this tick
+    fn on_pickup(&mut self, _player: PlayerId, _kind: PickupKind, _arena: &mut Arena) {}
+    /// Whether a team has completed the objective, which ends the match
+    fn is_over(&self) -> bool {
+        false
+    }
+    /// The team that won, or would win if the match ended now, `None` for a draw. The sole
+    /// top scorer unless the mode says otherwise
+    fn winner(&self, scores: &HashMap<Team, i32>) -> Option<Team> {
+        top_scorer(scores)
+    }
+    /// The state of the mode, as clients see it. Modes without objectives of their own
+    /// look like team deathmatch
+    fn objectives(&self) -> Objectives {
+        Objectives::TeamDeathmatch
+    }
+}
+
+/// The built-in game mode a match config asks for
+pub fn new(config: &GameModeConfig, teams: usize) -> Box<dyn GameMode> {
+    match config {
+        GameModeConfig::TeamDeathmatch => Box::new(TeamDeathmatch),
+        GameModeConfig::CaptureTheFlag(config) => Box::new(CaptureTheFlag::new(config, teams)),
+        GameModeConfig::KingOfTheHill(config) => Box::new(KingOfTheHill::new(config, teams)),
+    }
+}
+
+fn top_scorer(scores: &HashMap<Team, i32>) -> Option<Team> {
+    let top_score = scores.values().max()?;
+    let mut top_scorers = scores.iter().filter(|(_, score)| *score == top_score);
+    let (&team, _) = top_scorers.next()?;
+    top_scorers.next().is_none().then_some(team)
+}
+
+// the first team, in team order, whose progress reached `target`
+fn reached(progress: &[u32], target: Option<u32>) -> Option<Team> {
+    let target = target?;
+    progress.iter().position(|&progress| progress >= target).map(Team)
+}
+
+/// What a match is played for besides kills, for clients to draw and bots to go after
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Objectives {
+    TeamDeathmatch,
+    /// Flags and captures in team order
+    CaptureTheFlag { flags: Vec<Flag>, captures: Vec<u32> },
+    KingOfTheHill { hill: Hill },
+}
+
+/// A team's flag in capture the flag
+#[derive(Clone, Debug, Serialize)]
+pub struct Flag {
+    pub team: Team,
+    /// Where the flag is, following the drone carrying it
+    pub position: [f32;3],
+    /// Where the flag goes back to, and where the team brings enemy flags
+    pub base: [f32;3],
+    /// How close a drone has to come to the flag or to the base
+    pub radius: f32,
+    pub carrier: Option<PlayerId>,
+    pub dropped_ticks: u32, // game ticks lying where it was dropped
+}
+
+impl Flag {
+    pub fn at_base(&self) -> bool {
+        self.carrier.is_none() && self.position == self.base
+    }
+    fn reset(&mut self) {
+        self.position = self.base;
+        self.carrier = None;
+        self.dropped_ticks = 0;
+    }
+}
+
+/// The zone fought over in king of the hill
+#[derive(Clone, Debug, Serialize)]
+pub struct Hill {
+    pub center: [f32;3],
+    pub radius: f32,
+    pub holder: Option<Team>,
+    /// Game ticks each team has held the hill for, in team order
+    pub held_ticks: Vec<u32>,
+}
+
+struct TeamDeathmatch;
+
+impl GameMode for TeamDeathmatch {}
+
+struct CaptureTheFlag {
+    config: CaptureTheFlagConfig,
+    // one per team, in team order
+    flags: Vec<Flag>,
+    captures: Vec<u32>,
+}
+
+// bases spread evenly around the vertical axis, so that turning the arena like a team's
+// mirrored observation puts its base where team A's is
+fn default_base(team: Team, teams: usize) -> [f32;3] {
+    let angle = PI - team.0 as f32 / teams as f32 * TAU;
+    [SPAWN_AREA_SIZE * angle.cos(), SPAWN_AREA_SIZE * angle.sin(), 0.0]
+}
+
+impl CaptureTheFlag {
+    fn new(config: &CaptureTheFlagConfig, teams: usize) -> CaptureTheFlag {
+        CaptureTheFlag {
+            config: config.clone(),
+            flags: Team::all(teams)
+                .map(|team| {
+                    let base = config.bases.get(&team).copied().unwrap_or_else(|| default_base(team, teams));
+                    Flag {
+                        team,
+                        position: base,
+                        base,
+                        radius: config.radius,
+                        carrier: None,
+                        dropped_ticks: 0,
+                    }
+                })
+                .collect(),
+            captures: vec![0; teams],
+        }
+    }
+}
+
+impl GameMode for CaptureTheFlag {
+    fn on_tick(&mut self, arena: &mut Arena) {
+        let players = arena.players;
+        for flag in self.flags.iter_mut() {
+            if let Some(carrier) = flag.carrier {
+                flag.position = arena.player(carrier).position;
+            }
+        }
+        // a drone carries one flag at a time
+        let mut carriers: Vec<PlayerId> = self.flags.iter().filter_map(|flag| flag.carrier).collect();
+        for flag in self.flags.iter_mut().filter(|flag| flag.carrier.is_none()) {
+            let at_base = flag.at_base();
+            // the closest drone that can do something with the flag: return it if it's
+            // of the flag's team, take it otherwise
+            let toucher = players.iter()
+                .filter(|player| !player.is_dead && distance(&player.position, &flag.position) <= flag.radius)
+                .filter(|player| if player.team == flag.team { !at_base } else { !carriers.contains(&player.id()) })
+                .min_by(|p1, p2| {
+                    distance(&p1.position, &flag.position).total_cmp(&distance(&p2.position, &flag.position))
+                });
+            match toucher {
+                Some(player) if player.team == flag.team => {
+                    flag.reset();
+                    arena.events.push(Event::FlagReturned { player: Some(player.id()), flag: flag.team });
+                },
+                Some(player) => {
+                    flag.carrier = Some(player.id());
+                    flag.position = player.position;
+                    flag.dropped_ticks = 0;
+                    carriers.push(player.id());
+                    arena.events.push(Event::FlagTaken { player: player.id(), flag: flag.team });
+                },
+                None if !at_base => {
+                    flag.dropped_ticks += 1;
+                    if flag.dropped_ticks > self.config.return_ticks {
+                        flag.reset();
+                        arena.events.push(Event::FlagReturned { player: None, flag: flag.team });
+                    }
+                },
+                None => {},
+            }
+        }
+        // enemy flags only count once brought to a base that still has its own flag
+        for i in 0..self.flags.len() {
+            let Some(carrier) = self.flags[i].carrier else {
+                continue;
+            };
+            let home = &self.flags[carrier.team.0];
+            if !home.at_base() || distance(&arena.player(carrier).position, &home.base) > home.radius {
+                continue;
+            }
+            let flag = &mut self.flags[i];
+            flag.reset();
+            self.captures[carrier.team.0] += 1;
+            arena.events.push(Event::FlagCaptured { player: carrier, flag: flag.team });
+            arena.award(carrier, self.config.capture_reward);
+        }
+    }
+    fn on_kill(&mut self, victim: PlayerId, _killer: Option<PlayerId>, arena: &mut Arena) {
+        let Some(flag) = self.flags.iter_mut().find(|flag| flag.carrier == Some(victim)) else {
+            return;
+        };
+        flag.carrier = None;
+        flag.position = arena.player(victim).position;
+        flag.dropped_ticks = 0;
+        arena.events.push(Event::FlagDropped { player: victim, flag: flag.team });
+        // nobody could reach it out there
+        if !inside_game_area(&flag.position, &0.0) {
+            flag.reset();
+            arena.events.push(Event::FlagReturned { player: None, flag: flag.team });
+        }
+    }
+    fn is_over(&self) -> bool {
+        reached(&self.captures, self.config.captures_to_win).is_some()
+    }
+    // most captures, the top scorer if that's a tie
+    fn winner(&self, scores: &HashMap<Team, i32>) -> Option<Team> {
+        let most = self.captures.iter().max()?;
+        let mut leaders = self.captures.iter().enumerate().filter(|(_, captures)| *captures == most);
+        let (leader, _) = leaders.next()?;
+        if leaders.next().is_none() {
+            Some(Team(leader))
+        } else {
+            top_scorer(scores)
+        }
+    }
+    fn objectives(&self) -> Objectives {
+        Objectives::CaptureTheFlag {
+            flags: self.flags.clone(),
+            captures: self.captures.clone(),
+        }
+    }
+}
+
+struct KingOfTheHill {
+    config: KingOfTheHillConfig,
+    hill: Hill,
+}
+
+impl KingOfTheHill {
+    fn new(config: &KingOfTheHillConfig, teams: usize) -> KingOfTheHill {
+        KingOfTheHill {
+            config: config.clone(),
+            hill: Hill {
+                center: config.center,
+                radius: config.radius,
+                holder: None,
+                held_ticks: vec![0; teams],
+            },
+        }
+    }
+}
+
+impl GameMode for KingOfTheHill {
+    fn on_tick(&mut self, arena: &mut Arena) {
+        let players = arena.players;
+        let inside: Vec<&Player> = players.iter()
+            .filter(|player| !player.is_dead && distance(&player.position, &self.hill.center) <= self.hill.radius)
+            .collect();
+        let holder = match inside.first() {
+            Some(first) if inside.iter().all(|player| player.team == first.team) => Some(first.team),
+            _ => None,
+        };
+        if holder != self.hill.holder {
+            self.hill.holder = holder;
+            arena.events.push(Event::HillChanged { holder });
+        }
+        let Some(team) = holder else {
+            return;
+        };
+        let held_ticks = &mut self.hill.held_ticks[team.0];
+        *held_ticks += 1;
+        // points go to the team's first drone on the hill
+        if held_ticks.is_multiple_of(self.config.interval.max(1)) {
+            arena.award(inside[0].id(), self.config.points);
+        }
+    }
+    fn is_over(&self) -> bool {
+        reached(&self.hill.held_ticks, self.config.hold_to_win).is_some()
+    }
+    // whoever held the hill long enough, the top scorer otherwise
+    fn winner(&self, scores: &HashMap<Team, i32>) -> Option<Team> {
+        reached(&self.hill.held_ticks, self.config.hold_to_win).or_else(|| top_scorer(scores))
+    }
+    fn objectives(&self) -> Objectives {
+        Objectives::KingOfTheHill { hill: self.hill.clone() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MatchConfig;
+    use crate::gamestate::{EndReason, Gamestate};
+
+    // two teams, with team A's base at -x and team B's at +x
+    fn capture_the_flag() -> CaptureTheFlag {
+        let config = CaptureTheFlagConfig {
+            bases: HashMap::from([(Team(0), [-10.0, 0.0, 0.0]), (Team(1), [10.0, 0.0, 0.0])]),
+            return_ticks: 5,
+            ..CaptureTheFlagConfig::default()
+        };
+        CaptureTheFlag::new(&config, 2)
+    }
+
+    fn king_of_the_hill() -> KingOfTheHill {
+        KingOfTheHill::new(&KingOfTheHillConfig { interval: 3, points: 2, ..KingOfTheHillConfig::default() }, 2)
+    }
+
+    fn drone(team: usize, position: [f32;3]) -> Player {
+        Player::spawn(position, Team(team), 0, &MatchConfig::default())
+    }
+
+    // the scores and events of the match so far, as the arena every hook gets
+    struct Match {
+        scores: HashMap<Team, i32>,
+        events: Vec<Event>,
+    }
+
+    impl Match {
+        fn new() -> Match {
+            Match { scores: HashMap::from([(Team(0), 0), (Team(1), 0)]), events: vec![] }
+        }
+        fn tick(&mut self, mode: &mut dyn GameMode, players: &[Player]) {
+            self.events.clear();
+            mode.on_tick(&mut Arena { players, scores: &mut self.scores, events: &mut self.events });
+        }
+        fn kill(&mut self, mode: &mut dyn GameMode, players: &[Player], victim: PlayerId) {
+            self.events.clear();
+            mode.on_kill(victim, None, &mut Arena { players, scores: &mut self.scores, events: &mut self.events });
+        }
+    }
+
+    fn flag(mode: &CaptureTheFlag, team: usize) -> &Flag {
+        &mode.flags[team]
+    }
+
+    #[test]
+    fn flags_are_taken_and_captured_at_a_base_holding_its_own_flag() {
+        let mut mode = capture_the_flag();
+        let mut game = Match::new();
+        let mut players = [drone(0, [10.0, 0.0, 0.0])];
+        game.tick(&mut mode, &players);
+        assert!(matches!(game.events[..], [Event::FlagTaken { flag: Team(1), .. }]));
+        assert_eq!(flag(&mode, 1).carrier, Some(players[0].id()));
+        // carried along on the way home
+        players[0].position = [0.0, 0.0, 0.0];
+        game.tick(&mut mode, &players);
+        assert_eq!(flag(&mode, 1).position, [0.0, 0.0, 0.0]);
+        players[0].position = [-10.0, 0.0, 0.0];
+        game.tick(&mut mode, &players);
+        assert!(matches!(game.events[0], Event::FlagCaptured { flag: Team(1), .. }));
+        assert_eq!(mode.captures, [1, 0]);
+        assert_eq!(game.scores[&Team(0)], CaptureTheFlagConfig::default().capture_reward);
+        assert!(flag(&mode, 1).at_base());
+    }
+
+    #[test]
+    fn no_capture_while_the_home_flag_is_away() {
+        let mut mode = capture_the_flag();
+        let mut game = Match::new();
+        let mut players = [drone(0, [10.0, 0.0, 0.0]), drone(1, [-10.0, 0.0, 0.0])];
+        game.tick(&mut mode, &players);
+        assert!(flag(&mode, 0).carrier.is_some() && flag(&mode, 1).carrier.is_some());
+        players[0].position = [-10.0, 0.0, 0.0];
+        game.tick(&mut mode, &players);
+        assert_eq!(mode.captures, [0, 0]);
+    }
+
+    #[test]
+    fn dropped_flags_are_returned_by_their_team() {
+        let mut mode = capture_the_flag();
+        let mut game = Match::new();
+        let mut players = [drone(0, [10.0, 0.0, 0.0]), drone(1, [0.0, 8.0, 0.0])];
+        game.tick(&mut mode, &players);
+        players[0].position = [0.0, 0.0, 0.0];
+        players[0].is_dead = true;
+        game.kill(&mut mode, &players, players[0].id());
+        assert!(matches!(game.events[..], [Event::FlagDropped { flag: Team(1), .. }]));
+        assert_eq!(flag(&mode, 1).position, [0.0, 0.0, 0.0]);
+        players[1].position = [0.0, 1.0, 0.0];
+        game.tick(&mut mode, &players);
+        assert!(matches!(game.events[..], [Event::FlagReturned { player: Some(_), flag: Team(1) }]));
+        assert!(flag(&mode, 1).at_base());
+    }
+
+    #[test]
+    fn dropped_flags_go_home_on_their_own_after_a_while() {
+        let mut mode = capture_the_flag();
+        let mut game = Match::new();
+        let mut players = [drone(0, [10.0, 0.0, 0.0])];
+        game.tick(&mut mode, &players);
+        players[0].position = [0.0, 0.0, 0.0];
+        players[0].is_dead = true;
+        game.kill(&mut mode, &players, players[0].id());
+        for _ in 0..5 {
+            game.tick(&mut mode, &players);
+            assert!(game.events.is_empty());
+        }
+        game.tick(&mut mode, &players);
+        assert!(matches!(game.events[..], [Event::FlagReturned { player: None, flag: Team(1) }]));
+        assert!(flag(&mode, 1).at_base());
+    }
+
+    #[test]
+    fn holding_the_hill_scores_every_interval() {
+        let mut mode = king_of_the_hill();
+        let mut game = Match::new();
+        let players = [drone(0, [0.0, 0.0, 0.0]), drone(1, [0.0, 10.0, 0.0])];
+        game.tick(&mut mode, &players);
+        assert!(matches!(game.events[..], [Event::HillChanged { holder: Some(Team(0)) }]));
+        for _ in 0..5 {
+            game.tick(&mut mode, &players);
+        }
+        assert_eq!(mode.hill.held_ticks, [6, 0]);
+        assert_eq!(game.scores[&Team(0)], 4);
+        assert_eq!(game.scores[&Team(1)], 0);
+    }
+
+    #[test]
+    fn a_contested_hill_has_no_holder() {
+        let mut mode = king_of_the_hill();
+        let mut game = Match::new();
+        let mut players = [drone(0, [0.0, 0.0, 0.0]), drone(1, [0.0, 10.0, 0.0])];
+        game.tick(&mut mode, &players);
+        players[1].position = [0.0, 1.0, 0.0];
+        for _ in 0..6 {
+            game.tick(&mut mode, &players);
+        }
+        assert_eq!(mode.hill.holder, None);
+        assert_eq!(mode.hill.held_ticks, [1, 0]);
+        assert_eq!(game.scores[&Team(0)], 0);
+        // dead drones don't contest it
+        players[1].is_dead = true;
+        game.tick(&mut mode, &players);
+        assert_eq!(mode.hill.holder, Some(Team(0)));
+    }
+
+    // a mode of its own, won by whoever scores first
+    struct FirstBlood {
+        winner: Option<Team>,
+    }
+
+    impl GameMode for FirstBlood {
+        fn on_tick(&mut self, arena: &mut Arena) {
+            self.winner = self.winner.or_else(|| arena.scores.iter().find(|(_, &score)| score != 0).map(|(&team, _)| team));
+            if self.winner.is_none() {
+                arena.award(arena.players[0].id(), 1);
+            }
+        }
+        fn is_over(&self) -> bool {
+            self.winner.is_some()
+        }
+        fn winner(&self, _scores: &HashMap<Team, i32>) -> Option<Team> {
+            self.winner
+        }
+    }
+
+    #[test]
+    fn matches_play_an_installed_game_mode() {
+        let mut gamestate = Gamestate::new(MatchConfig::default(), 0);
+        gamestate.set_game_mode(Box::new(FirstBlood { winner: None }));
+        assert!(matches!(gamestate.objectives(), Objectives::TeamDeathmatch));
+        gamestate.step(&[]);
+        assert_eq!(gamestate.end_reason(), None);
+        gamestate.step(&[]);
+        assert_eq!(gamestate.end_reason(), Some(EndReason::Objective));
+        assert_eq!(gamestate.winner(), Some(Team(0)));
+    }
+}
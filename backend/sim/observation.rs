@@ -0,0 +1,616 @@
+use std::f32::consts::TAU;
+use std::io::{self, Write};
+use serde::Serialize;
+use crate::config::{HealthConfig, PickupKind, WeaponKind, WeaponsConfig};
+use crate::events::{Event, PlayerId};
+use crate::fog::{LastSeen, Visibility};
+use crate::modes::Objectives;
+use crate::gamestate::{
+    Bullet, Gamestate, Mine, Missile, Obstacle, Pickup, Player, Team,
+    GAME_AREA_SIZE, MAX_PLAYER_RESPAWN_TIMER,
+};
+use crate::vec3::distance_squared;
+
+// Observations from one team's point of view: its own drones and score come first, and
+// every team can have the arena turned around the vertical axis so that all teams see
+// themselves from the same canonical side of it. Team n of N is turned n/N of a full
+// turn, so with two teams team B sees the arena turned by half. One policy can then play
+// any team. With fog of war, anything the team can't currently see is left out, except
+// for the objectives of the game mode, which every team always knows about.
+
+// sine and cosine of `turn` full turns, exact for quarter turns so that two and four
+// team matches keep the arena's walls where they were
+fn turn_sin_cos(turn: f32) -> (f32, f32) {
+    if turn == 0.25 {
+        (1.0, 0.0)
+    } else if turn == 0.5 {
+        (0.0, -1.0)
+    } else if turn == 0.75 {
+        (-1.0, 0.0)
+    } else {
+        (turn * TAU).sin_cos()
+    }
+}
+
+// turn around the vertical axis, which keeps the coordinate system right-handed
+fn turn_vec3(v: &[f32;3], (sin, cos): (f32, f32)) -> [f32;3] {
+    [cos * v[0] - sin * v[1], sin * v[0] + cos * v[1], v[2]]
+}
+
+// under fog of war a team only learns about events involving its own drones or things it can see
+fn event_visible(event: &Event, team: Team, visibility: &Visibility, team_size: usize) -> bool {
+    let sees_player = |player: &PlayerId| {
+        player.team == team || visibility.drones[player.team.0 * team_size + player.index]
+    };
+    match event {
+        Event::BulletFired { shooter, bullet } => sees_player(shooter) || visibility.bullets.contains(bullet),
+        Event::MissileFired { shooter, missile } => sees_player(shooter) || visibility.missiles.contains(missile),
+        Event::LaserFired { shooter, .. } | Event::MissileExploded { shooter, .. } => sees_player(shooter),
+        Event::MineLaid { owner, .. } | Event::MineExploded { owner, .. } => sees_player(owner),
+        Event::PlayerDamaged { victim, attacker, .. } => sees_player(victim) || attacker.as_ref().is_some_and(sees_player),
+        Event::PlayerKilled { victim, killer, .. } => sees_player(victim) || killer.as_ref().is_some_and(sees_player),
+        Event::FriendlyFire { victim, shooter } => sees_player(victim) || sees_player(shooter),
+        Event::BulletHitObstacle { bullet, obstacle } => {
+            visibility.bullets.contains(bullet) || visibility.obstacles.contains(obstacle)
+        },
+        Event::ObstacleSpawned { obstacle } => visibility.obstacles.contains(obstacle),
+        Event::PickupSpawned { pickup } => visibility.pickups.contains(pickup),
+        Event::FlagTaken { .. }
+        | Event::FlagDropped { .. }
+        | Event::FlagReturned { .. }
+        | Event::FlagCaptured { .. }
+        | Event::HillChanged { .. } => true,
+        Event::PlayerRespawned { player }
+        | Event::OutOfBounds { player }
+        | Event::PickupCollected { player, .. }
+        | Event::ScoreChanged { player, .. } => sees_player(player),
+    }
+}
+
+// 0 when there's nothing to be a fraction of
+fn fraction(value: f32, max: f32) -> f32 {
+    if max > 0.0 { value / max } else { 0.0 }
+}
+
+/// The match as seen by one team
+#[derive(Clone, Debug, Serialize)]
+pub struct TeamObservation {
+    pub team: Team,
+    /// Whether coordinates and headings have been turned for this team, by `team / teams`
+    /// of a full turn around the vertical axis
+    pub mirrored: bool,
+    pub ticks_progressed: u32,
+    pub max_game_ticks: u32,
+    /// Only used for encoding, clients get it once in the handshake
+    #[serde(skip)]
+    pub health: HealthConfig,
+    /// Only used for encoding, clients get it once in the handshake
+    #[serde(skip)]
+    pub weapons: WeaponsConfig,
+    pub my_score: i32,
+    /// Highest score of any other team
+    pub their_score: i32,
+    pub my_drones: Vec<Player>,
+    /// Drones of every other team in team order, `None` for those hidden by fog of war
+    pub enemy_drones: Vec<Option<Player>>,
+    /// Where each enemy drone was last seen, ordered like `enemy_drones`. Only tracked
+    /// with fog of war
+    pub enemy_last_seen: Vec<Option<LastSeen>>,
+    /// Ordered by guid
+    pub obstacles: Vec<Obstacle>,
+    /// Ordered by guid
+    pub bullets: Vec<Bullet>,
+    /// Ordered by guid
+    pub missiles: Vec<Missile>,
+    /// Ordered by guid
+    pub mines: Vec<Mine>,
+    /// Ordered by guid
+    pub pickups: Vec<Pickup>,
+    /// Never hidden by fog of war
+    pub objectives: Objectives,
+    /// Events of the last tick, with fog of war only those the team could have noticed
+    pub events: Vec<Event>,
+}
+
+impl TeamObservation {
+    /// Observe the match as `team`, turning the arena for every team but the first if
+    /// `mirror` is set
+    pub fn new(gamestate: &Gamestate, team: Team, mirror: bool) -> TeamObservation {
+        let mirrored = mirror && team != Team::A;
+        let turn = if mirrored { team.0 as f32 / gamestate.num_teams() as f32 } else { 0.0 };
+        let rotation = turn_sin_cos(turn);
+        let visibility = gamestate.visibility(team);
+        let view_player = |player: &Player| {
+            let mut player = player.clone();
+            if mirrored {
+                player.position = turn_vec3(&player.position, rotation);
+                player.prev_position = turn_vec3(&player.prev_position, rotation);
+                player.velocity = turn_vec3(&player.velocity, rotation);
+                player.rot_y += turn * TAU;
+            }
+            player
+        };
+        let mut obstacles: Vec<Obstacle> = gamestate.obstacles()
+            .filter(|obstacle| visibility.is_none_or(|visibility| visibility.obstacles.contains(&obstacle.guid)))
+            .map(|obstacle| {
+                let mut obstacle = obstacle.clone();
+                if mirrored {
+                    obstacle.position = turn_vec3(&obstacle.position, rotation);
+                    obstacle.velocity = turn_vec3(&obstacle.velocity, rotation);
+                }
+                obstacle
+            })
+            .collect();
+        obstacles.sort_by_key(|obstacle| obstacle.guid);
+        let mut bullets: Vec<Bullet> = gamestate.bullets()
+            .filter(|bullet| visibility.is_none_or(|visibility| visibility.bullets.contains(&bullet.guid)))
+            .map(|bullet| {
+                let mut bullet = bullet.clone();
+                if mirrored {
+                    bullet.position = turn_vec3(&bullet.position, rotation);
+                    bullet.prev_position = turn_vec3(&bullet.prev_position, rotation);
+                    bullet.velocity = turn_vec3(&bullet.velocity, rotation);
+                }
+                bullet
+            })
+            .collect();
+        bullets.sort_by_key(|bullet| bullet.guid);
+        let mut missiles: Vec<Missile> = gamestate.missiles()
+            .filter(|missile| visibility.is_none_or(|visibility| visibility.missiles.contains(&missile.guid)))
+            .map(|missile| {
+                let mut missile = missile.clone();
+                if mirrored {
+                    missile.position = turn_vec3(&missile.position, rotation);
+                    missile.prev_position = turn_vec3(&missile.prev_position, rotation);
+                    missile.velocity = turn_vec3(&missile.velocity, rotation);
+                }
+                missile
+            })
+            .collect();
+        missiles.sort_by_key(|missile| missile.guid);
+        let mut mines: Vec<Mine> = gamestate.mines()
+            .filter(|mine| visibility.is_none_or(|visibility| visibility.mines.contains(&mine.guid)))
+            .map(|mine| {
+                let mut mine = mine.clone();
+                if mirrored {
+                    mine.position = turn_vec3(&mine.position, rotation);
+                }
+                mine
+            })
+            .collect();
+        mines.sort_by_key(|mine| mine.guid);
+        let mut pickups: Vec<Pickup> = gamestate.pickups()
+            .filter(|item| visibility.is_none_or(|visibility| visibility.pickups.contains(&item.guid)))
+            .map(|item| {
+                let mut item = item.clone();
+                if mirrored {
+                    item.position = turn_vec3(&item.position, rotation);
+                }
+                item
+            })
+            .collect();
+        pickups.sort_by_key(|item| item.guid);
+        let mut objectives = gamestate.objectives().clone();
+        if mirrored {
+            match &mut objectives {
+                Objectives::TeamDeathmatch => {},
+                Objectives::CaptureTheFlag { flags, .. } => {
+                    for flag in flags.iter_mut() {
+                        flag.position = turn_vec3(&flag.position, rotation);
+                        flag.base = turn_vec3(&flag.base, rotation);
+                    }
+                },
+                Objectives::KingOfTheHill { hill } => hill.center = turn_vec3(&hill.center, rotation),
+            }
+        }
+        // drones of other teams along with where they are in `Gamestate::players`
+        let enemies: Vec<(usize, &Player)> = gamestate.players()
+            .iter()
+            .enumerate()
+            .filter(|(_, player)| player.team != team)
+            .collect();
+        TeamObservation {
+            team,
+            mirrored,
+            ticks_progressed: gamestate.ticks_progressed(),
+            max_game_ticks: gamestate.config().max_game_ticks,
+            health: gamestate.config().health.clone(),
+            weapons: gamestate.config().weapons.clone(),
+            my_score: gamestate.score(team),
+            their_score: gamestate.teams()
+                .filter(|&other| other != team)
+                .map(|other| gamestate.score(other))
+                .max()
+                .unwrap_or(0),
+            my_drones: gamestate.team_players(team).iter().map(view_player).collect(),
+            enemy_drones: enemies.iter()
+                .map(|&(i, player)| {
+                    let visible = visibility.is_none_or(|visibility| visibility.drones[i]);
+                    visible.then(|| view_player(player))
+                })
+                .collect(),
+            enemy_last_seen: enemies.iter()
+                .map(|&(i, _)| {
+                    let last_seen = visibility.and_then(|visibility| visibility.last_seen[i].as_ref());
+                    last_seen.map(|last_seen| {
+                        let mut last_seen = last_seen.clone();
+                        if mirrored {
+                            last_seen.position = turn_vec3(&last_seen.position, rotation);
+                            last_seen.velocity = turn_vec3(&last_seen.velocity, rotation);
+                        }
+                        last_seen
+                    })
+                })
+                .collect(),
+            obstacles,
+            bullets,
+            missiles,
+            mines,
+            pickups,
+            objectives,
+            events: gamestate.events()
+                .iter()
+                .filter(|event| visibility.is_none_or(|visibility| {
+                    event_visible(event, team, visibility, gamestate.team_size())
+                }))
+                .cloned()
+                .collect(),
+        }
+    }
+    // distance from a point to this team's closest drone, preferring living drones
+    fn distance_to_team(&self, position: &[f32;3]) -> f32 {
+        let any_alive = self.my_drones.iter().any(|drone| !drone.is_dead);
+        self.my_drones.iter()
+            .filter(|drone| !any_alive || !drone.is_dead)
+            .map(|drone| distance_squared(&drone.position, position))
+            .fold(f32::INFINITY, f32::min)
+    }
+}
+
+pub const GLOBAL_FEATURES: &[&str] = &["time_fraction", "my_score", "their_score"];
+pub const DRONE_FEATURES: &[&str] = &[
+    "visible", "alive", "x", "y", "z", "vx", "vy", "vz", "cos_rot_y", "sin_rot_y",
+    "sin_pitch", "cos_roll", "sin_roll",
+    "ammo_fraction", "reload_fraction", "fire_ready", "respawn_fraction",
+    "health_fraction", "shield_fraction", "speed_boosted", "fire_rate_boosted",
+    "gun", "laser", "missile", "mine",
+];
+pub const OBSTACLE_FEATURES: &[&str] = &["present", "x", "y", "z", "vx", "vy", "vz", "radius"];
+pub const BULLET_FEATURES: &[&str] = &["present", "x", "y", "z", "vx", "vy", "vz", "mine"];
+pub const MISSILE_FEATURES: &[&str] = &["present", "x", "y", "z", "vx", "vy", "vz", "mine"];
+pub const MINE_FEATURES: &[&str] = &["present", "x", "y", "z", "armed", "mine"];
+pub const PICKUP_FEATURES: &[&str] = &[
+    "present", "x", "y", "z", "ammo", "health", "shield", "speed_boost", "rapid_fire",
+];
+pub const FLAG_FEATURES: &[&str] = &[
+    "present", "x", "y", "z", "base_x", "base_y", "base_z", "at_base", "carried", "mine",
+];
+pub const HILL_FEATURES: &[&str] = &["present", "x", "y", "z", "radius", "held", "mine"];
+pub const LAST_SEEN_FEATURES: &[&str] = &["known", "x", "y", "z", "vx", "vy", "vz", "age_fraction"];
+
+/// One block of a flat observation: `rows` entities of `features.len()` values each,
+/// starting at `offset`
+#[derive(Clone, Debug, Serialize)]
+pub struct LayoutField {
+    pub name: &'static str,
+    pub offset: usize,
+    pub rows: usize,
+    pub features: &'static [&'static str],
+}
+
+/// Fixed-size `f32` encoding of a [`TeamObservation`]
+///
+/// Positions are divided by the half-width of the game area. Weapon features describe the
+/// drone's selected weapon. Only the obstacles, bullets, missiles, mines and items closest
+/// to the team's drones are encoded, nearest first; missing entities
+/// are zero-padded and have their `present` feature set to 0, as are enemy drones hidden
+/// by fog of war. Last sightings of enemy drones are only filled in with fog of war.
+/// Drones beyond `team_size` per team are left out, missing ones are zero-padded.
+/// Flags come one per team, the team's own first, and are only present in capture the
+/// flag, like the hill in king of the hill.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct FlatLayout {
+    /// Teams encoded, should match the number of teams of the match config
+    pub teams: usize,
+    /// Drones encoded per team, should match the team size of the match config
+    pub team_size: usize,
+    pub nearest_obstacles: usize,
+    pub nearest_bullets: usize,
+    pub nearest_missiles: usize,
+    pub nearest_mines: usize,
+    pub nearest_pickups: usize,
+}
+
+impl Default for FlatLayout {
+    fn default() -> FlatLayout {
+        FlatLayout {
+            teams: 2,
+            team_size: 2,
+            nearest_obstacles: 8,
+            nearest_bullets: 8,
+            nearest_missiles: 4,
+            nearest_mines: 4,
+            nearest_pickups: 4,
+        }
+    }
+}
+
+impl FlatLayout {
+    /// Every block of the encoding, in order
+    pub fn fields(&self) -> Vec<LayoutField> {
+        let blocks: [(&'static str, usize, &'static [&'static str]); 11] = [
+            ("globals", 1, GLOBAL_FEATURES),
+            ("my_drones", self.team_size, DRONE_FEATURES),
+            ("enemy_drones", self.enemy_drones(), DRONE_FEATURES),
+            ("enemy_last_seen", self.enemy_drones(), LAST_SEEN_FEATURES),
+            ("obstacles", self.nearest_obstacles, OBSTACLE_FEATURES),
+            ("bullets", self.nearest_bullets, BULLET_FEATURES),
+            ("missiles", self.nearest_missiles, MISSILE_FEATURES),
+            ("mines", self.nearest_mines, MINE_FEATURES),
+            ("pickups", self.nearest_pickups, PICKUP_FEATURES),
+            ("flags", self.teams, FLAG_FEATURES),
+            ("hill", 1, HILL_FEATURES),
+        ];
+        let mut offset = 0;
+        blocks.into_iter()
+            .map(|(name, rows, features)| {
+                let field = LayoutField { name, offset, rows, features };
+                offset += rows * features.len();
+                field
+            })
+            .collect()
+    }
+    fn enemy_drones(&self) -> usize {
+        self.teams.saturating_sub(1) * self.team_size
+    }
+    /// Number of `f32` values in one encoded observation
+    pub fn size(&self) -> usize {
+        self.fields().iter().map(|field| field.rows * field.features.len()).sum()
+    }
+    pub fn encode(&self, observation: &TeamObservation) -> Vec<f32> {
+        let mut out = Vec::with_capacity(self.size());
+        self.encode_into(observation, &mut out);
+        out
+    }
+    /// Append the encoded observation to `out`
+    pub fn encode_into(&self, observation: &TeamObservation, out: &mut Vec<f32>) {
+        out.push(observation.ticks_progressed as f32 / observation.max_game_ticks.max(1) as f32);
+        out.push(observation.my_score as f32);
+        out.push(observation.their_score as f32);
+        let my_drones = (0..self.team_size).map(|i| observation.my_drones.get(i));
+        let enemy_drones = (0..self.enemy_drones())
+            .map(|i| observation.enemy_drones.get(i).and_then(Option::as_ref));
+        for drone in my_drones.chain(enemy_drones) {
+            let Some(drone) = drone else {
+                out.extend_from_slice(&[0.0; DRONE_FEATURES.len()]);
+                continue;
+            };
+            let weapon = drone.weapon();
+            let (ammo, reload, ready) = match weapon {
+                Some(weapon) => {
+                    let ammo = observation.weapons.ammo(weapon.kind);
+                    (
+                        fraction(weapon.ammo as f32, ammo.max as f32),
+                        fraction(weapon.reload_timer as f32, ammo.reload_ticks as f32).min(1.0),
+                        weapon.cooled_down(&observation.weapons, drone.fire_rate_boost),
+                    )
+                },
+                None => (0.0, 0.0, false),
+            };
+            // one-hot kind of selected weapon
+            let mut kind = [0.0; 4];
+            if let Some(weapon) = weapon {
+                kind[match weapon.kind {
+                    WeaponKind::Gun => 0,
+                    WeaponKind::Laser => 1,
+                    WeaponKind::Missile => 2,
+                    WeaponKind::Mine => 3,
+                }] = 1.0;
+            }
+            out.extend_from_slice(&[
+                1.0,
+                if drone.is_dead { 0.0 } else { 1.0 },
+                drone.position[0] / GAME_AREA_SIZE,
+                drone.position[1] / GAME_AREA_SIZE,
+                drone.position[2] / GAME_AREA_SIZE,
+                drone.velocity[0],
+                drone.velocity[1],
+                drone.velocity[2],
+                drone.rot_y.cos(),
+                drone.rot_y.sin(),
+                drone.pitch.sin(),
+                drone.roll.cos(),
+                drone.roll.sin(),
+                ammo,
+                reload,
+                if ready { 1.0 } else { 0.0 },
+                if drone.is_dead {
+                    (drone.respawn_timer as f32 / MAX_PLAYER_RESPAWN_TIMER as f32).min(1.0)
+                } else {
+                    0.0
+                },
+                fraction(drone.health, observation.health.max_health),
+                fraction(drone.shield, observation.health.max_shield),
+                if drone.speed_boost_timer > 0 { 1.0 } else { 0.0 },
+                if drone.fire_rate_boost_timer > 0 { 1.0 } else { 0.0 },
+            ]);
+            out.extend_from_slice(&kind);
+        }
+        for i in 0..self.enemy_drones() {
+            match observation.enemy_last_seen.get(i).and_then(Option::as_ref) {
+                Some(last_seen) => out.extend_from_slice(&[
+                    1.0,
+                    last_seen.position[0] / GAME_AREA_SIZE,
+                    last_seen.position[1] / GAME_AREA_SIZE,
+                    last_seen.position[2] / GAME_AREA_SIZE,
+                    last_seen.velocity[0],
+                    last_seen.velocity[1],
+                    last_seen.velocity[2],
+                    observation.ticks_progressed.saturating_sub(last_seen.tick) as f32
+                        / observation.max_game_ticks.max(1) as f32,
+                ]),
+                None => out.extend_from_slice(&[0.0; LAST_SEEN_FEATURES.len()]),
+            }
+        }
+        let mut obstacles: Vec<(f32, &Obstacle)> = observation.obstacles.iter()
+            .map(|obstacle| (observation.distance_to_team(&obstacle.position), obstacle))
+            .collect();
+        obstacles.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for i in 0..self.nearest_obstacles {
+            match obstacles.get(i) {
+                Some((_, obstacle)) => out.extend_from_slice(&[
+                    1.0,
+                    obstacle.position[0] / GAME_AREA_SIZE,
+                    obstacle.position[1] / GAME_AREA_SIZE,
+                    obstacle.position[2] / GAME_AREA_SIZE,
+                    obstacle.velocity[0],
+                    obstacle.velocity[1],
+                    obstacle.velocity[2],
+                    obstacle.radius / GAME_AREA_SIZE,
+                ]),
+                None => out.extend_from_slice(&[0.0; OBSTACLE_FEATURES.len()]),
+            }
+        }
+        let mut bullets: Vec<(f32, &Bullet)> = observation.bullets.iter()
+            .map(|bullet| (observation.distance_to_team(&bullet.position), bullet))
+            .collect();
+        bullets.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for i in 0..self.nearest_bullets {
+            match bullets.get(i) {
+                Some((_, bullet)) => out.extend_from_slice(&[
+                    1.0,
+                    bullet.position[0] / GAME_AREA_SIZE,
+                    bullet.position[1] / GAME_AREA_SIZE,
+                    bullet.position[2] / GAME_AREA_SIZE,
+                    bullet.velocity[0],
+                    bullet.velocity[1],
+                    bullet.velocity[2],
+                    if bullet.team == observation.team { 1.0 } else { 0.0 },
+                ]),
+                None => out.extend_from_slice(&[0.0; BULLET_FEATURES.len()]),
+            }
+        }
+        let mut missiles: Vec<(f32, &Missile)> = observation.missiles.iter()
+            .map(|missile| (observation.distance_to_team(&missile.position), missile))
+            .collect();
+        missiles.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for i in 0..self.nearest_missiles {
+            match missiles.get(i) {
+                Some((_, missile)) => out.extend_from_slice(&[
+                    1.0,
+                    missile.position[0] / GAME_AREA_SIZE,
+                    missile.position[1] / GAME_AREA_SIZE,
+                    missile.position[2] / GAME_AREA_SIZE,
+                    missile.velocity[0],
+                    missile.velocity[1],
+                    missile.velocity[2],
+                    if missile.team == observation.team { 1.0 } else { 0.0 },
+                ]),
+                None => out.extend_from_slice(&[0.0; MISSILE_FEATURES.len()]),
+            }
+        }
+        let mut mines: Vec<(f32, &Mine)> = observation.mines.iter()
+            .map(|mine| (observation.distance_to_team(&mine.position), mine))
+            .collect();
+        mines.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for i in 0..self.nearest_mines {
+            match mines.get(i) {
+                Some((_, mine)) => out.extend_from_slice(&[
+                    1.0,
+                    mine.position[0] / GAME_AREA_SIZE,
+                    mine.position[1] / GAME_AREA_SIZE,
+                    mine.position[2] / GAME_AREA_SIZE,
+                    if mine.armed { 1.0 } else { 0.0 },
+                    if mine.team == observation.team { 1.0 } else { 0.0 },
+                ]),
+                None => out.extend_from_slice(&[0.0; MINE_FEATURES.len()]),
+            }
+        }
+        let mut pickups: Vec<(f32, &Pickup)> = observation.pickups.iter()
+            .map(|item| (observation.distance_to_team(&item.position), item))
+            .collect();
+        pickups.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for i in 0..self.nearest_pickups {
+            let Some((_, item)) = pickups.get(i) else {
+                out.extend_from_slice(&[0.0; PICKUP_FEATURES.len()]);
+                continue;
+            };
+            // one-hot kind of item
+            let mut kind = [0.0; 5];
+            kind[match item.kind {
+                PickupKind::Ammo => 0,
+                PickupKind::Health { .. } => 1,
+                PickupKind::Shield { .. } => 2,
+                PickupKind::SpeedBoost { .. } => 3,
+                PickupKind::RapidFire { .. } => 4,
+            }] = 1.0;
+            out.extend_from_slice(&[
+                1.0,
+                item.position[0] / GAME_AREA_SIZE,
+                item.position[1] / GAME_AREA_SIZE,
+                item.position[2] / GAME_AREA_SIZE,
+            ]);
+            out.extend_from_slice(&kind);
+        }
+        let (flags, hill) = match &observation.objectives {
+            Objectives::CaptureTheFlag { flags, .. } => (flags.as_slice(), None),
+            Objectives::KingOfTheHill { hill } => (&[][..], Some(hill)),
+            Objectives::TeamDeathmatch => (&[][..], None),
+        };
+        // the team's own flag first, then the others in team order
+        let flags = (0..self.teams).map(|i| {
+            let team = if i == 0 { observation.team.0 } else if i <= observation.team.0 { i - 1 } else { i };
+            flags.get(team)
+        });
+        for flag in flags {
+            match flag {
+                Some(flag) => out.extend_from_slice(&[
+                    1.0,
+                    flag.position[0] / GAME_AREA_SIZE,
+                    flag.position[1] / GAME_AREA_SIZE,
+                    flag.position[2] / GAME_AREA_SIZE,
+                    flag.base[0] / GAME_AREA_SIZE,
+                    flag.base[1] / GAME_AREA_SIZE,
+                    flag.base[2] / GAME_AREA_SIZE,
+                    if flag.at_base() { 1.0 } else { 0.0 },
+                    if flag.carrier.is_some() { 1.0 } else { 0.0 },
+                    if flag.team == observation.team { 1.0 } else { 0.0 },
+                ]),
+                None => out.extend_from_slice(&[0.0; FLAG_FEATURES.len()]),
+            }
+        }
+        match hill {
+            Some(hill) => out.extend_from_slice(&[
+                1.0,
+                hill.center[0] / GAME_AREA_SIZE,
+                hill.center[1] / GAME_AREA_SIZE,
+                hill.center[2] / GAME_AREA_SIZE,
+                hill.radius / GAME_AREA_SIZE,
+                if hill.holder.is_some() { 1.0 } else { 0.0 },
+                if hill.holder == Some(observation.team) { 1.0 } else { 0.0 },
+            ]),
+            None => out.extend_from_slice(&[0.0; HILL_FEATURES.len()]),
+        }
+    }
+}
+
+/// Write `data` as a little-endian `f32` NumPy `.npy` array of the given shape
+pub fn write_npy(writer: &mut impl Write, data: &[f32], shape: &[usize]) -> io::Result<()> {
+    assert_eq!(shape.iter().product::<usize>(), data.len(), "shape does not match data");
+    let shape = match shape {
+        [length] => format!("({},)", length),
+        _ => format!("({})", shape.iter().map(usize::to_string).collect::<Vec<_>>().join(", ")),
+    };
+    let mut header = format!("{{'descr': '<f4', 'fortran_order': False, 'shape': {}, }}", shape);
+    // magic, version and header length take 10 bytes, the whole header must align to 64
+    let padding = 63 - (10 + header.len()) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+    writer.write_all(b"\x93NUMPY\x01\x00")?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    for value in data {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,146 @@
+use rand::Rng;
+use crate::config::{ObstacleConfig, SpawnPattern};
+use crate::gamestate::{GAME_AREA_SIZE, OBSTACLE_AREA_SIZE};
+use crate::vec3::length_vec3;
+
+// Obstacles appear in the shell between the arena and the edge of the obstacle area,
+// never inside the arena itself, so no drone is ever crushed by an obstacle appearing
+// on top of it. Each one flies towards a random point inside the arena.
+
+// one of the six sides of the arena: the axis it's perpendicular to, and which end
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Side {
+    axis: usize,
+    sign: f32,
+}
+
+impl Side {
+    fn random(rng: &mut impl Rng) -> Side {
+        Side {
+            axis: rng.gen_range(0..3),
+            sign: if rng.gen_bool(0.5) { 1.0 } else { -1.0 },
+        }
+    }
+}
+
+/// Where and how a new obstacle enters
+pub(crate) struct Spawn {
+    pub position: [f32;3],
+    pub velocity: [f32;3],
+    pub radius: f32,
+}
+
+/// Decides when and where obstacles spawn
+#[derive(Clone, Debug)]
+pub(crate) struct ObstacleSpawner {
+    // game ticks until the next spawn
+    spawn_timer: u32,
+    // side storms come from, only used by the storm pattern
+    storm_side: Side,
+    storm_calm: bool,
+    // game ticks left in the current storm or calm
+    storm_timer: u32,
+}
+
+impl ObstacleSpawner {
+    pub(crate) fn new(config: &ObstacleConfig, rng: &mut impl Rng) -> ObstacleSpawner {
+        let (first_spawn, storm_timer) = match config.pattern {
+            SpawnPattern::Stream { interval } | SpawnPattern::Waves { interval, .. } => (interval, 0),
+            SpawnPattern::Storm { interval, duration, .. } => (interval, duration),
+        };
+        ObstacleSpawner {
+            // don't always start with an obstacle on the first tick
+            spawn_timer: rng.gen_range(0..=first_spawn / 4),
+            storm_side: Side::random(rng),
+            storm_calm: false,
+            storm_timer,
+        }
+    }
+    /// Obstacles to spawn this tick, `progress` is how far into the match it is from 0 to 1
+    pub(crate) fn tick(&mut self, config: &ObstacleConfig, progress: f32, rng: &mut impl Rng) -> Vec<Spawn> {
+        // intervals shrink as the spawn rate ramps up over the match
+        let rate = 1.0 + (config.ramp_up - 1.0) * progress.clamp(0.0, 1.0);
+        let scaled = |interval: u32| ((interval as f32 / rate).round() as u32).max(1);
+        match config.pattern {
+            SpawnPattern::Stream { interval } => {
+                if !self.count_down() {
+                    return vec![];
+                }
+                let interval = scaled(interval);
+                self.spawn_timer = rng.gen_range(interval * 3 / 4..=interval * 5 / 4);
+                let side = Side::random(rng);
+                vec![spawn(config, side, rng)]
+            },
+            SpawnPattern::Waves { interval, count } => {
+                if !self.count_down() {
+                    return vec![];
+                }
+                self.spawn_timer = scaled(interval);
+                (0..count).map(|_| {
+                    let side = Side::random(rng);
+                    spawn(config, side, rng)
+                }).collect()
+            },
+            SpawnPattern::Storm { interval, duration, calm } => {
+                if self.storm_timer == 0 {
+                    if self.storm_calm {
+                        // the next storm comes from a different side
+                        let previous_side = self.storm_side;
+                        while self.storm_side == previous_side {
+                            self.storm_side = Side::random(rng);
+                        }
+                        self.storm_timer = duration;
+                    } else {
+                        self.storm_timer = calm;
+                    }
+                    self.storm_calm = !self.storm_calm;
+                }
+                self.storm_timer = self.storm_timer.saturating_sub(1);
+                if self.storm_calm || !self.count_down() {
+                    return vec![];
+                }
+                self.spawn_timer = scaled(interval);
+                vec![spawn(config, self.storm_side, rng)]
+            },
+        }
+    }
+    // whether it's time to spawn
+    fn count_down(&mut self) -> bool {
+        if self.spawn_timer > 0 {
+            self.spawn_timer -= 1;
+            false
+        } else {
+            true
+        }
+    }
+}
+
+fn spawn(config: &ObstacleConfig, side: Side, rng: &mut impl Rng) -> Spawn {
+    // bounds given the wrong way round still make a range
+    let radius = rng.gen_range(config.min_radius.min(config.max_radius)..=config.min_radius.max(config.max_radius));
+    // just clear of the arena on the chosen side, anywhere within the obstacle area otherwise
+    let mut position = [
+        rng.gen_range(-OBSTACLE_AREA_SIZE..=OBSTACLE_AREA_SIZE),
+        rng.gen_range(-OBSTACLE_AREA_SIZE..=OBSTACLE_AREA_SIZE),
+        rng.gen_range(-OBSTACLE_AREA_SIZE..=OBSTACLE_AREA_SIZE),
+    ];
+    let closest = (GAME_AREA_SIZE + radius).min(OBSTACLE_AREA_SIZE);
+    position[side.axis] = side.sign * rng.gen_range(closest..=OBSTACLE_AREA_SIZE);
+    let target = [
+        rng.gen_range(-GAME_AREA_SIZE..=GAME_AREA_SIZE),
+        rng.gen_range(-GAME_AREA_SIZE..=GAME_AREA_SIZE),
+        rng.gen_range(-GAME_AREA_SIZE..=GAME_AREA_SIZE),
+    ];
+    let direction = [target[0] - position[0], target[1] - position[1], target[2] - position[2]];
+    let distance = length_vec3(&direction);
+    let speed = rng.gen_range(config.min_speed.min(config.max_speed)..=config.min_speed.max(config.max_speed));
+    Spawn {
+        position,
+        velocity: [
+            direction[0] / distance * speed,
+            direction[1] / distance * speed,
+            direction[2] / distance * speed,
+        ],
+        radius,
+    }
+}
@@ -0,0 +1,86 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use crate::config::{MatchConfig, PickupConfig, PickupKind};
+use crate::gamestate::{Pickup, Player, PLAYER_RADIUS};
+use crate::spawn::SPAWN_AREA_SIZE;
+use crate::vec3::distance_to_segment;
+
+// Items spawn one at a time, either at a random one of the configured spawn points that
+// doesn't hold an item yet, or anywhere away from the edge of the arena. While the arena
+// holds as many items as it may, the next one waits until a drone collects one.
+
+/// Where and what a new item is
+pub(crate) struct Spawn {
+    pub position: [f32;3],
+    pub kind: PickupKind,
+}
+
+/// Decides when and where items spawn
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PickupSpawner {
+    // game ticks until the next spawn
+    spawn_timer: u32,
+}
+
+impl PickupSpawner {
+    /// Item to spawn this tick, if any, given the items already in the arena
+    pub(crate) fn tick(&mut self, config: &PickupConfig, items: &[&Pickup], rng: &mut impl Rng) -> Option<Spawn> {
+        if self.spawn_timer > 0 {
+            self.spawn_timer -= 1;
+            return None;
+        }
+        if items.len() >= config.max_items {
+            return None;
+        }
+        let kind = *config.kinds.choose(rng)?;
+        let position = if config.spawn_points.is_empty() {
+            [
+                rng.gen_range(-SPAWN_AREA_SIZE..=SPAWN_AREA_SIZE),
+                rng.gen_range(-SPAWN_AREA_SIZE..=SPAWN_AREA_SIZE),
+                rng.gen_range(-SPAWN_AREA_SIZE..=SPAWN_AREA_SIZE),
+            ]
+        } else {
+            let free: Vec<&[f32;3]> = config.spawn_points.iter()
+                .filter(|point| !items.iter().any(|item| item.position == **point))
+                .collect();
+            **free.choose(rng)?
+        };
+        self.spawn_timer = config.interval;
+        Some(Spawn { position, kind })
+    }
+}
+
+/// The closest living drone that touched the item this tick, as an index into `players`
+pub(crate) fn collector(item: &Pickup, players: &[Player]) -> Option<usize> {
+    players.iter()
+        .enumerate()
+        .filter(|(_, player)| !player.is_dead)
+        .map(|(i, player)| (i, distance_to_segment(&item.position, &player.prev_position, &player.position)))
+        .filter(|(_, distance)| *distance <= item.radius + PLAYER_RADIUS)
+        .min_by(|(_, d1), (_, d2)| d1.total_cmp(d2))
+        .map(|(i, _)| i)
+}
+
+/// Give the drone whatever the item does
+pub(crate) fn apply(kind: PickupKind, player: &mut Player, config: &MatchConfig) {
+    match kind {
+        PickupKind::Ammo => {
+            for weapon in player.weapons.iter_mut() {
+                weapon.ammo = config.weapons.ammo(weapon.kind).max;
+                weapon.reload_timer = 0;
+            }
+        },
+        PickupKind::Health { amount } => {
+            player.health = (player.health + amount).min(config.health.max_health);
+        },
+        PickupKind::Shield { amount } => player.shield += amount,
+        PickupKind::SpeedBoost { factor, duration } => {
+            player.speed_boost = factor;
+            player.speed_boost_timer = duration;
+        },
+        PickupKind::RapidFire { factor, duration } => {
+            player.fire_rate_boost = factor;
+            player.fire_rate_boost_timer = duration;
+        },
+    }
+}
@@ -0,0 +1,67 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use serde::Serialize;
+use crate::config::MatchConfig;
+use crate::gamestate::Gamestate;
+use crate::stats::MatchResult;
+
+// Replays are a log of the match's states in JSON lines: a header with the seed and
+// config the match was created with, then the gamestate after every tick, each carrying
+// the events of that tick, and finally a footer with the match result and every drone's
+// stats once the match is over. Controls aren't recorded, so a replay is for watching and
+// analysing a match, not for simulating it again.
+
+#[derive(Serialize)]
+struct ReplayFooter {
+    result: MatchResult,
+}
+
+#[derive(Serialize)]
+struct ReplayHeader<'a> {
+    seed: u64,
+    config: &'a MatchConfig,
+}
+
+/// Records a match tick by tick
+pub struct ReplayWriter<W: Write> {
+    writer: W,
+}
+
+impl ReplayWriter<BufWriter<File>> {
+    /// Start recording the match into a new file at `path`
+    pub fn create(path: &Path, gamestate: &Gamestate) -> io::Result<ReplayWriter<BufWriter<File>>> {
+        ReplayWriter::new(BufWriter::new(File::create(path)?), gamestate)
+    }
+}
+
+impl<W: Write> ReplayWriter<W> {
+    /// Write the header and the initial gamestate
+    pub fn new(writer: W, gamestate: &Gamestate) -> io::Result<ReplayWriter<W>> {
+        let mut replay = ReplayWriter { writer };
+        replay.write_line(&ReplayHeader {
+            seed: gamestate.seed(),
+            config: gamestate.config(),
+        })?;
+        replay.record(gamestate)?;
+        Ok(replay)
+    }
+    /// Record the gamestate after a tick
+    pub fn record(&mut self, gamestate: &Gamestate) -> io::Result<()> {
+        self.write_line(gamestate)
+    }
+    /// Write the footer, the match should be over
+    pub fn finish(&mut self, gamestate: &Gamestate) -> io::Result<()> {
+        self.write_line(&ReplayFooter {
+            result: gamestate.result(),
+        })?;
+        self.flush()
+    }
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+    fn write_line(&mut self, value: &impl Serialize) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, value)?;
+        self.writer.write_all(b"\n")
+    }
+}
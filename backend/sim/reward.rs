@@ -0,0 +1,156 @@
+use serde::{Serialize, Deserialize};
+use crate::events::Event;
+use crate::gamestate::{Gamestate, Player, Team};
+use crate::vec3::{distance_squared, dot_vec3, length_vec3};
+
+// Rewards for reinforcement learning, computed after every tick from the events of the
+// tick and the resulting state. Which built-in reward function a match uses is part of
+// its config, training code can also bring its own by implementing RewardFunction.
+
+/// Rewards for one tick, indexed by team
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Rewards {
+    pub teams: Vec<f32>,
+    /// Indexed `[team][drone]`, like [`crate::Gamestate::team_players`]
+    pub drones: Vec<Vec<f32>>,
+}
+
+impl Rewards {
+    /// No reward for anyone, with room for `teams` teams of `team_size` drones
+    pub fn new(teams: usize, team_size: usize) -> Rewards {
+        Rewards {
+            teams: vec![0.0; teams],
+            drones: vec![vec![0.0; team_size]; teams],
+        }
+    }
+    pub fn team(&self, team: Team) -> f32 {
+        self.teams[team.0]
+    }
+    pub fn drones(&self, team: Team) -> &[f32] {
+        &self.drones[team.0]
+    }
+}
+
+/// Turns the outcome of a tick into rewards
+pub trait RewardFunction: Send + Sync {
+    /// Rewards for the tick that was just computed, `gamestate` holds its events
+    fn rewards(&self, gamestate: &Gamestate) -> Rewards;
+}
+
+/// Terms of the shaped reward added on top of the score delta
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShapedReward {
+    /// Reward per tick for every living drone
+    pub survival: f32,
+    /// Reward per tick for a living drone facing straight at the closest living enemy,
+    /// scaled down to `-aiming` when facing away. Uses the true state even under fog of war
+    pub aiming: f32,
+}
+
+impl Default for ShapedReward {
+    fn default() -> ShapedReward {
+        ShapedReward {
+            survival: 0.01,
+            aiming: 0.01,
+        }
+    }
+}
+
+/// Built-in reward functions
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RewardConfig {
+    /// 1 for the winner and -1 for every other team on the tick the match ends, or -1 for
+    /// every team below the top score after a draw, 0 otherwise
+    Sparse,
+    /// Points won or lost during the tick, drones are credited with the points they caused
+    #[default]
+    ScoreDelta,
+    /// Score delta with survival and aiming terms added
+    Shaped(ShapedReward),
+}
+
+impl RewardFunction for RewardConfig {
+    fn rewards(&self, gamestate: &Gamestate) -> Rewards {
+        match self {
+            RewardConfig::Sparse => sparse(gamestate),
+            RewardConfig::ScoreDelta => score_delta(gamestate),
+            RewardConfig::Shaped(shaped) => shaped.rewards(gamestate),
+        }
+    }
+}
+
+fn sparse(gamestate: &Gamestate) -> Rewards {
+    let mut rewards = Rewards::new(gamestate.num_teams(), gamestate.team_size());
+    if !gamestate.is_over() {
+        return rewards;
+    }
+    let winner = gamestate.winner();
+    let top_score = gamestate.teams().map(|team| gamestate.score(team)).max().unwrap_or(0);
+    for team in gamestate.teams() {
+        let outcome = if winner == Some(team) {
+            1.0
+        } else if winner.is_some() || gamestate.score(team) < top_score {
+            -1.0
+        } else {
+            0.0
+        };
+        rewards.teams[team.0] = outcome;
+        rewards.drones[team.0] = vec![outcome; gamestate.team_size()];
+    }
+    rewards
+}
+
+fn score_delta(gamestate: &Gamestate) -> Rewards {
+    let mut rewards = Rewards::new(gamestate.num_teams(), gamestate.team_size());
+    for event in gamestate.events() {
+        if let Event::ScoreChanged { player, points } = event {
+            rewards.teams[player.team.0] += *points as f32;
+            rewards.drones[player.team.0][player.index] += *points as f32;
+        }
+    }
+    rewards
+}
+
+// cosine of the angle between where a drone's nose points and the direction to a target
+fn aim(player: &Player, target: &[f32;3]) -> f32 {
+    let to_target = [
+        target[0] - player.position[0],
+        target[1] - player.position[1],
+        target[2] - player.position[2],
+    ];
+    let distance = length_vec3(&to_target);
+    if distance == 0.0 {
+        return 1.0;
+    }
+    let [forward, _, _] = player.axes();
+    dot_vec3(&forward, &to_target) / distance
+}
+
+impl RewardFunction for ShapedReward {
+    fn rewards(&self, gamestate: &Gamestate) -> Rewards {
+        let mut rewards = score_delta(gamestate);
+        for team in gamestate.teams() {
+            for (i, player) in gamestate.team_players(team).iter().enumerate() {
+                if player.is_dead {
+                    continue;
+                }
+                let mut reward = self.survival;
+                let closest_enemy = gamestate.players()
+                    .iter()
+                    .filter(|enemy| enemy.team != team && !enemy.is_dead)
+                    .min_by(|e1, e2| {
+                        distance_squared(&player.position, &e1.position)
+                            .total_cmp(&distance_squared(&player.position, &e2.position))
+                    });
+                if let Some(enemy) = closest_enemy {
+                    reward += self.aiming * aim(player, &enemy.position);
+                }
+                rewards.drones[team.0][i] += reward;
+                rewards.teams[team.0] += reward;
+            }
+        }
+        rewards
+    }
+}
@@ -0,0 +1,192 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use crate::config::{SpawnConfig, SpawnZone};
+use crate::gamestate::{Bullet, Obstacle, GAME_AREA_SIZE, PLAYER_RADIUS};
+use crate::vec3::{distance, distance_to_segment};
+
+// Spawnpoints are picked from a grid of candidates laid over the spawn zone, each jittered
+// within its cell so spawns don't always land on the same points. The winner is the
+// candidate with the most clearance, i.e. whose closest threat is furthest away: other
+// drones, and obstacles and bullets anywhere along where they will be over the next few
+// ticks. Ties, which only happen with nothing around, are broken at random.
+
+// don't spawn too close to the edge of the arena
+pub(crate) const SPAWN_AREA_SIZE: f32 = GAME_AREA_SIZE * 0.75;
+
+/// Everything a new drone should keep away from
+pub(crate) struct Threats<'a> {
+    /// Positions of living drones
+    pub drones: &'a [[f32;3]],
+    pub obstacles: &'a [&'a Obstacle],
+    pub bullets: &'a [&'a Bullet],
+}
+
+impl Threats<'_> {
+    // distance between a drone at `point` and the closest thing that could kill it soon,
+    // giving up as soon as it's no more than `floor`
+    fn clearance(&self, point: &[f32;3], lookahead: f32, floor: f32) -> f32 {
+        let drones = self.drones.iter()
+            .map(|drone| distance(point, drone) - PLAYER_RADIUS * 2.0);
+        let obstacles = self.obstacles.iter().map(|obstacle| {
+            let ahead = along(&obstacle.position, &obstacle.velocity, lookahead);
+            distance_to_segment(point, &obstacle.position, &ahead) - obstacle.radius - PLAYER_RADIUS
+        });
+        let bullets = self.bullets.iter().map(|bullet| {
+            let ahead = along(&bullet.position, &bullet.velocity, lookahead);
+            distance_to_segment(point, &bullet.position, &ahead) - PLAYER_RADIUS
+        });
+        let mut clearance = f32::INFINITY;
+        for threat in drones.chain(obstacles).chain(bullets) {
+            clearance = clearance.min(threat);
+            if clearance <= floor {
+                break;
+            }
+        }
+        clearance
+    }
+}
+
+fn along(position: &[f32;3], velocity: &[f32;3], ticks: f32) -> [f32;3] {
+    [
+        position[0] + velocity[0] * ticks,
+        position[1] + velocity[1] * ticks,
+        position[2] + velocity[2] * ticks,
+    ]
+}
+
+// the zone clipped to where drones may spawn at all, the whole spawn area without one
+fn clip(zone: Option<&SpawnZone>) -> SpawnZone {
+    let mut clipped = SpawnZone {
+        min: [-SPAWN_AREA_SIZE;3],
+        max: [SPAWN_AREA_SIZE;3],
+    };
+    if let Some(zone) = zone {
+        for axis in [0, 1, 2] {
+            let min = zone.min[axis].min(zone.max[axis]).clamp(-SPAWN_AREA_SIZE, SPAWN_AREA_SIZE);
+            let max = zone.min[axis].max(zone.max[axis]).clamp(-SPAWN_AREA_SIZE, SPAWN_AREA_SIZE);
+            clipped.min[axis] = min;
+            clipped.max[axis] = max;
+        }
+    }
+    clipped
+}
+
+/// The fairest point of `zone` to spawn a drone at
+pub(crate) fn fair_spawnpoint(
+    config: &SpawnConfig,
+    zone: Option<&SpawnZone>,
+    threats: &Threats,
+    rng: &mut impl Rng
+) -> [f32;3] {
+    let zone = clip(zone);
+    let cells = config.candidates.max(1);
+    let cell_size = [
+        (zone.max[0] - zone.min[0]) / cells as f32,
+        (zone.max[1] - zone.min[1]) / cells as f32,
+        (zone.max[2] - zone.min[2]) / cells as f32,
+    ];
+    let mut candidates = Vec::with_capacity(cells.pow(3) as usize);
+    for x in 0..cells {
+        for y in 0..cells {
+            for z in 0..cells {
+                candidates.push([
+                    zone.min[0] + (x as f32 + rng.gen::<f32>()) * cell_size[0],
+                    zone.min[1] + (y as f32 + rng.gen::<f32>()) * cell_size[1],
+                    zone.min[2] + (z as f32 + rng.gen::<f32>()) * cell_size[2],
+                ]);
+            }
+        }
+    }
+    candidates.shuffle(rng);
+    let lookahead = config.lookahead as f32;
+    // candidates that can't beat the best so far are dropped early
+    let mut best = (f32::NEG_INFINITY, [0.0;3]);
+    for candidate in candidates {
+        let clearance = threats.clearance(&candidate, lookahead, best.0);
+        if clearance > best.0 {
+            best = (clearance, candidate);
+        }
+    }
+    best.1
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+    use crate::events::PlayerId;
+    use crate::gamestate::Team;
+    use super::*;
+
+    #[test]
+    fn spawnpoint_keeps_clear_of_a_crowded_scene() {
+        let zone = SpawnZone { min: [-12.0, -4.0, -12.0], max: [4.0, 12.0, 4.0] };
+        let config = SpawnConfig::default();
+        let drones = [[0.0, 4.0, -4.0], [-10.0, 10.0, 2.0], [2.0, -2.0, -10.0]];
+        // flying right through the zone over the next few ticks
+        let obstacle = Obstacle {
+            guid: 0,
+            position: [-30.0, 10.0, -10.0],
+            radius: 3.0,
+            velocity: [0.5, 0.0, 0.0],
+        };
+        let bullet = Bullet {
+            team: Team::B,
+            shooter: PlayerId { team: Team::B, index: 0 },
+            guid: 0,
+            position: [2.0, 10.0, 30.0],
+            prev_position: [2.0, 10.0, 30.25],
+            velocity: [0.0, 0.0, -0.25],
+            age: 0,
+            distance_traveled: 0.0,
+        };
+        let threats = Threats {
+            drones: &drones,
+            obstacles: &[&obstacle],
+            bullets: &[&bullet],
+        };
+        let lookahead = config.lookahead as f32;
+        // the best a spawnpoint can do, close enough, searched point by point
+        let steps = 64;
+        let mut best = f32::NEG_INFINITY;
+        for x in 0..=steps {
+            for y in 0..=steps {
+                for z in 0..=steps {
+                    let point = [
+                        zone.min[0] + (zone.max[0] - zone.min[0]) * x as f32 / steps as f32,
+                        zone.min[1] + (zone.max[1] - zone.min[1]) * y as f32 / steps as f32,
+                        zone.min[2] + (zone.max[2] - zone.min[2]) * z as f32 / steps as f32,
+                    ];
+                    best = best.max(threats.clearance(&point, lookahead, f32::NEG_INFINITY));
+                }
+            }
+        }
+        // some candidate lands in the same cell as the best point, at most a cell
+        // diagonal away, and clearance changes no faster than the distance moved
+        let cells = config.candidates as f32;
+        let cell_size = [
+            (zone.max[0] - zone.min[0]) / cells,
+            (zone.max[1] - zone.min[1]) / cells,
+            (zone.max[2] - zone.min[2]) / cells,
+        ];
+        let cell_diagonal = distance(&[0.0;3], &cell_size);
+        // there is room to spawn clear of everything
+        assert!(best - cell_diagonal > 0.0);
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let spawnpoint = fair_spawnpoint(&config, Some(&zone), &threats, &mut rng);
+            for axis in [0, 1, 2] {
+                assert!((zone.min[axis]..=zone.max[axis]).contains(&spawnpoint[axis]), "{spawnpoint:?} outside the zone");
+            }
+            let clearance = threats.clearance(&spawnpoint, lookahead, f32::NEG_INFINITY);
+            assert!(clearance >= best - cell_diagonal, "clearance {clearance} at {spawnpoint:?}, best {best}");
+        }
+    }
+
+    #[test]
+    fn zones_are_clipped_to_the_spawn_area() {
+        let zone = SpawnZone { min: [30.0, 5.0, -30.0], max: [-30.0, -5.0, 30.0] };
+        let clipped = clip(Some(&zone));
+        assert_eq!(clipped.min, [-SPAWN_AREA_SIZE, -5.0, -SPAWN_AREA_SIZE]);
+        assert_eq!(clipped.max, [SPAWN_AREA_SIZE, 5.0, SPAWN_AREA_SIZE]);
+    }
+}
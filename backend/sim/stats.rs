@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use serde::Serialize;
+use crate::events::{DeathCause, Event, PlayerId};
+use crate::gamestate::{EndReason, Team};
+
+/// How often a drone died of each cause
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DeathCounts {
+    pub bullet: u32,
+    pub laser: u32,
+    pub missile: u32,
+    pub mine: u32,
+    pub obstacle: u32,
+    pub collision: u32,
+    pub out_of_bounds: u32,
+}
+
+impl DeathCounts {
+    pub fn total(&self) -> u32 {
+        self.bullet + self.laser + self.missile + self.mine
+            + self.obstacle + self.collision + self.out_of_bounds
+    }
+    fn add(&mut self, cause: DeathCause) {
+        match cause {
+            DeathCause::Bullet => self.bullet += 1,
+            DeathCause::Laser => self.laser += 1,
+            DeathCause::Missile => self.missile += 1,
+            DeathCause::Mine => self.mine += 1,
+            DeathCause::Obstacle => self.obstacle += 1,
+            DeathCause::Collision => self.collision += 1,
+            DeathCause::OutOfBounds => self.out_of_bounds += 1,
+        }
+    }
+}
+
+/// What one drone has done so far in the match
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PlayerStats {
+    /// Shots of any weapon, counting mines dropped
+    pub shots_fired: u32,
+    /// Hits on enemy drones by this drone's weapons
+    pub hits: u32,
+    /// Enemy drones killed, whether shot or rammed
+    pub kills: u32,
+    pub deaths: DeathCounts,
+    /// Hits on teammates by this drone's bullets
+    pub friendly_fire: u32,
+    /// Damage done to enemy drones, by weapons and collisions
+    pub damage_dealt: f32,
+    /// Damage taken from anything, including what the shield absorbed
+    pub damage_taken: f32,
+    /// Items collected
+    pub pickups: u32,
+    /// Enemy flags brought home, in capture the flag
+    pub captures: u32,
+    pub distance_traveled: f32,
+    pub ticks_alive: u32,
+}
+
+impl PlayerStats {
+    /// Fraction of shots fired that hit an enemy
+    pub fn accuracy(&self) -> f32 {
+        if self.shots_fired == 0 {
+            0.0
+        } else {
+            self.hits as f32 / self.shots_fired as f32
+        }
+    }
+    // count whatever part `player` played in an event
+    pub(crate) fn record(&mut self, player: PlayerId, event: &Event) {
+        match *event {
+            Event::BulletFired { shooter, .. }
+            | Event::LaserFired { shooter, .. }
+            | Event::MissileFired { shooter, .. }
+            | Event::MineLaid { owner: shooter, .. } if shooter == player => self.shots_fired += 1,
+            Event::PlayerDamaged { victim, attacker, cause, damage, .. } => {
+                if victim == player {
+                    self.damage_taken += damage;
+                } else if attacker == Some(player) && victim.team != player.team {
+                    self.damage_dealt += damage;
+                    if cause.is_weapon() {
+                        self.hits += 1;
+                    }
+                }
+            },
+            Event::PlayerKilled { victim, killer, cause } => {
+                if victim == player {
+                    self.deaths.add(cause);
+                } else if killer == Some(player) && victim.team != player.team {
+                    self.kills += 1;
+                }
+            },
+            Event::FriendlyFire { shooter, .. } if shooter == player => self.friendly_fire += 1,
+            Event::PickupCollected { player: collector, .. } if collector == player => self.pickups += 1,
+            Event::FlagCaptured { player: capturer, .. } if capturer == player => self.captures += 1,
+            _ => {},
+        }
+    }
+}
+
+/// Stats of one drone at the end of a match
+#[derive(Clone, Debug, Serialize)]
+pub struct PlayerResult {
+    pub player: PlayerId,
+    pub stats: PlayerStats,
+}
+
+/// How a match ended and how every drone performed
+#[derive(Clone, Debug, Serialize)]
+pub struct MatchResult {
+    pub seed: u64,
+    pub ticks_progressed: u32,
+    pub end_reason: Option<EndReason>,
+    /// Decided by the game mode, `None` for a draw
+    pub winner: Option<Team>,
+    pub scores: HashMap<Team, i32>,
+    pub players: Vec<PlayerResult>,
+}
@@ -0,0 +1,51 @@
+// Vector math on plain `[f32;3]`, shared by the simulation and everything built on it.
+
+pub(crate) fn dot_vec3(v1: &[f32;3], v2: &[f32;3]) -> f32 {
+    v1[0] * v2[0] + v1[1] * v2[1] + v1[2] * v2[2]
+}
+
+pub(crate) fn add_vec3(v1: &mut [f32;3], v2: &[f32;3]) {
+    v1[0] += v2[0];
+    v1[1] += v2[1];
+    v1[2] += v2[2];
+}
+
+pub(crate) fn length_vec3(v: &[f32;3]) -> f32 {
+    dot_vec3(v, v).sqrt()
+}
+
+// unit vector from `to` towards `from`, straight up if they are the same point
+pub(crate) fn direction_vec3(from: &[f32;3], to: &[f32;3]) -> [f32;3] {
+    let difference = [from[0] - to[0], from[1] - to[1], from[2] - to[2]];
+    let length = length_vec3(&difference);
+    if length == 0.0 {
+        return [0.0, 0.0, 1.0];
+    }
+    [difference[0] / length, difference[1] / length, difference[2] / length]
+}
+
+// cheaper than `distance` where only comparisons matter
+pub(crate) fn distance_squared(v1: &[f32;3], v2: &[f32;3]) -> f32 {
+    (v1[0] - v2[0]).powi(2) + (v1[1] - v2[1]).powi(2) + (v1[2] - v2[2]).powi(2)
+}
+
+pub(crate) fn distance(v1: &[f32;3], v2: &[f32;3]) -> f32 {
+    distance_squared(v1, v2).sqrt()
+}
+
+// the point on the segment between v1 and v2 closest to `point`
+pub(crate) fn closest_on_segment(point: &[f32;3], v1: &[f32;3], v2: &[f32;3]) -> [f32;3] {
+    let segment = [v2[0] - v1[0], v2[1] - v1[1], v2[2] - v1[2]];
+    let length_squared = dot_vec3(&segment, &segment);
+    if length_squared == 0.0 {
+        return *v1;
+    }
+    let from_start = [point[0] - v1[0], point[1] - v1[1], point[2] - v1[2]];
+    let t = (dot_vec3(&from_start, &segment) / length_squared).clamp(0.0, 1.0);
+    [v1[0] + segment[0] * t, v1[1] + segment[1] * t, v1[2] + segment[2] * t]
+}
+
+// distance from a point to the closest point on the segment between v1 and v2
+pub(crate) fn distance_to_segment(point: &[f32;3], v1: &[f32;3], v2: &[f32;3]) -> f32 {
+    distance(point, &closest_on_segment(point, v1, v2))
+}
@@ -0,0 +1,285 @@
+use std::num::NonZeroUsize;
+use std::sync::{Arc, mpsc::{self, Receiver, Sender}};
+use std::thread;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use crate::config::MatchConfig;
+use crate::gamestate::{Controls, EndReason, Gamestate};
+use crate::modes::GameMode;
+use crate::observation::{FlatLayout, TeamObservation};
+use crate::reward::{RewardFunction, Rewards};
+
+/// Controls for every drone of one environment, indexed `[team][drone]`. Drones without
+/// controls stay idle
+pub type EnvControls = Vec<Vec<Controls>>;
+
+/// Makes the game mode of every new episode, see [`VecEnv::set_game_mode`]
+pub type GameModeFactory = dyn Fn(&MatchConfig) -> Box<dyn GameMode> + Send + Sync;
+
+// a new episode, playing the custom game mode if there is one
+fn new_episode(config: &MatchConfig, seed: u64, game_mode: Option<&GameModeFactory>) -> Gamestate {
+    let mut state = Gamestate::new(config.clone(), seed);
+    if let Some(game_mode) = game_mode {
+        state.set_game_mode(game_mode(config));
+    }
+    state
+}
+
+// one environment along with the source of seeds for its future episodes
+struct Slot {
+    state: Gamestate,
+    seeds: StdRng,
+}
+
+/// Outcome of stepping every environment once, indexed by environment
+#[derive(Default)]
+pub struct VecStep {
+    /// Rewards for the step, from the reward function of the match config unless
+    /// replaced with [`VecEnv::set_reward_function`]
+    pub rewards: Vec<Rewards>,
+    /// Why the environment's episode ended this step, if it did
+    pub end_reasons: Vec<Option<EndReason>>,
+    /// Last state of each episode that ended this step, the environment itself has
+    /// already been reset
+    pub final_states: Vec<Option<Gamestate>>,
+    /// Flat observations of every environment for every team after the step, and after
+    /// the reset for environments whose episode ended, shaped like
+    /// [`VecEnv::flat_observations`]
+    pub observations: Vec<f32>,
+}
+
+// a block of environments handed to a worker to step
+struct Job {
+    slots: Vec<Slot>,
+    controls: Vec<EnvControls>,
+    layout: FlatLayout,
+    mirror: bool,
+    reward_function: Arc<dyn RewardFunction>,
+    game_mode: Option<Arc<GameModeFactory>>,
+}
+
+// the environments of a job handed back, along with how their step went
+struct Done {
+    slots: Vec<Slot>,
+    step: VecStep,
+}
+
+// a thread kept for the life of the VecEnv, it exits once its job channel is dropped
+struct Worker {
+    jobs: Sender<Job>,
+    done: Receiver<Done>,
+}
+
+impl Worker {
+    fn spawn(config: Arc<MatchConfig>) -> Worker {
+        let (jobs, job_receiver) = mpsc::channel::<Job>();
+        let (done_sender, done) = mpsc::channel();
+        thread::spawn(move || {
+            for job in job_receiver {
+                if done_sender.send(work(&config, job)).is_err() {
+                    break;
+                }
+            }
+        });
+        Worker { jobs, done }
+    }
+}
+
+fn work(config: &MatchConfig, job: Job) -> Done {
+    let Job { mut slots, controls, layout, mirror, reward_function, game_mode } = job;
+    let mut step = VecStep::default();
+    for (slot, controls) in slots.iter_mut().zip(&controls) {
+        slot.state.step(controls);
+        step.rewards.push(reward_function.rewards(&slot.state));
+        let end_reason = slot.state.end_reason();
+        step.end_reasons.push(end_reason);
+        step.final_states.push(end_reason.map(|_| {
+            let next_episode = new_episode(config, slot.seeds.gen(), game_mode.as_deref());
+            std::mem::replace(&mut slot.state, next_episode)
+        }));
+        for team in slot.state.teams() {
+            layout.encode_into(&TeamObservation::new(&slot.state, team, mirror), &mut step.observations);
+        }
+    }
+    Done { slots, step }
+}
+
+/// Many independent matches stepped together on a pool of worker threads
+///
+/// Environments reset themselves as soon as their episode ends, so every call to
+/// [`VecEnv::step`] steps every environment. Episode seeds are derived from the seed
+/// the `VecEnv` was created with, so a run can be reproduced exactly.
+pub struct VecEnv {
+    config: Arc<MatchConfig>,
+    slots: Vec<Slot>,
+    workers: Vec<Worker>,
+    reward_function: Arc<dyn RewardFunction>,
+    game_mode: Option<Arc<GameModeFactory>>,
+}
+
+impl VecEnv {
+    /// Create `num_envs` environments, stepped using up to `num_threads` threads
+    /// (all available cores if `None`)
+    pub fn new(config: MatchConfig, num_envs: usize, seed: u64, num_threads: Option<usize>) -> VecEnv {
+        let num_threads = num_threads
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, NonZeroUsize::get))
+            .max(1);
+        let config = Arc::new(config);
+        let mut vec_env = VecEnv {
+            reward_function: Arc::new(config.reward.clone()),
+            game_mode: None,
+            workers: (0..num_threads).map(|_| Worker::spawn(Arc::clone(&config))).collect(),
+            config,
+            slots: vec![],
+        };
+        vec_env.reset(num_envs, seed);
+        vec_env
+    }
+    /// Start a new episode in every environment
+    pub fn reset(&mut self, num_envs: usize, seed: u64) {
+        let mut seeds = StdRng::seed_from_u64(seed);
+        self.slots = (0..num_envs)
+            .map(|_| {
+                let mut env_seeds = StdRng::seed_from_u64(seeds.gen());
+                Slot {
+                    state: new_episode(&self.config, env_seeds.gen(), self.game_mode.as_deref()),
+                    seeds: env_seeds,
+                }
+            })
+            .collect();
+    }
+    /// Compute rewards with a custom reward function instead of the configured one
+    pub fn set_reward_function(&mut self, reward_function: Box<dyn RewardFunction>) {
+        self.reward_function = Arc::from(reward_function);
+    }
+    /// Play a custom game mode instead of the configured one, `game_mode` makes a fresh
+    /// one for every episode. Episodes already running switch to it straight away
+    pub fn set_game_mode(&mut self, game_mode: impl Fn(&MatchConfig) -> Box<dyn GameMode> + Send + Sync + 'static) {
+        for slot in self.slots.iter_mut() {
+            slot.state.set_game_mode(game_mode(&self.config));
+        }
+        self.game_mode = Some(Arc::new(game_mode));
+    }
+    pub fn num_envs(&self) -> usize {
+        self.slots.len()
+    }
+    /// Current state of every environment
+    pub fn states(&self) -> impl ExactSizeIterator<Item = &Gamestate> {
+        self.slots.iter().map(|slot| &slot.state)
+    }
+    pub fn state(&self, env: usize) -> &Gamestate {
+        &self.slots[env].state
+    }
+    /// Flat observations of every environment for every team, in one buffer of shape
+    /// `(num_envs, teams, layout.size())`
+    pub fn flat_observations(&self, layout: &FlatLayout, mirror: bool) -> Vec<f32> {
+        let mut out = Vec::with_capacity(self.slots.len() * self.config.teams * layout.size());
+        for slot in &self.slots {
+            for team in slot.state.teams() {
+                layout.encode_into(&TeamObservation::new(&slot.state, team, mirror), &mut out);
+            }
+        }
+        out
+    }
+    /// Step every environment with its controls, `controls` must hold one entry per
+    /// environment. Observations are encoded with `layout`, see [`TeamObservation::new`]
+    /// for `mirror`
+    pub fn step(&mut self, controls: &[EnvControls], layout: &FlatLayout, mirror: bool) -> VecStep {
+        assert_eq!(controls.len(), self.slots.len(), "need controls for every environment");
+        // contiguous blocks of environments, one per worker
+        let chunk_size = self.slots.len().div_ceil(self.workers.len()).max(1);
+        let mut slots = std::mem::take(&mut self.slots);
+        let mut busy = 0;
+        for (worker, controls) in self.workers.iter().zip(controls.chunks(chunk_size)) {
+            let rest = slots.split_off(controls.len());
+            worker.jobs.send(Job {
+                slots,
+                controls: controls.to_vec(),
+                layout: *layout,
+                mirror,
+                reward_function: Arc::clone(&self.reward_function),
+                game_mode: self.game_mode.clone(),
+            }).unwrap();
+            slots = rest;
+            busy += 1;
+        }
+        // in environment order, whichever worker finishes first
+        let mut step = VecStep::default();
+        for worker in &self.workers[..busy] {
+            let done = worker.done.recv().expect("worker thread panicked");
+            self.slots.extend(done.slots);
+            step.rewards.extend(done.step.rewards);
+            step.end_reasons.extend(done.step.end_reasons);
+            step.final_states.extend(done.step.final_states);
+            step.observations.extend(done.step.observations);
+        }
+        step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn short_match() -> MatchConfig {
+        MatchConfig { max_game_ticks: 3, ..MatchConfig::default() }
+    }
+
+    fn idle(vec_env: &mut VecEnv, layout: &FlatLayout) -> VecStep {
+        let controls = vec![vec![]; vec_env.num_envs()];
+        vec_env.step(&controls, layout, false)
+    }
+
+    #[test]
+    fn reset_starts_reproducible_episodes() {
+        let mut vec_env = VecEnv::new(short_match(), 3, 5, Some(2));
+        let seeds: Vec<u64> = vec_env.states().map(Gamestate::seed).collect();
+        assert!(seeds[0] != seeds[1] && seeds[1] != seeds[2]);
+        idle(&mut vec_env, &FlatLayout::default());
+        vec_env.reset(4, 5);
+        assert_eq!(vec_env.num_envs(), 4);
+        assert!(vec_env.states().all(|state| state.ticks_progressed() == 0));
+        // the same seed gives the same environments, however many there are
+        let reset_seeds: Vec<u64> = vec_env.states().map(Gamestate::seed).collect();
+        assert_eq!(reset_seeds[..3], seeds[..]);
+    }
+
+    #[test]
+    fn finished_episodes_reset_themselves() {
+        let layout = FlatLayout::default();
+        let mut vec_env = VecEnv::new(short_match(), 2, 1, Some(2));
+        let seeds: Vec<u64> = vec_env.states().map(Gamestate::seed).collect();
+        for _ in 0..2 {
+            let step = idle(&mut vec_env, &layout);
+            assert!(step.end_reasons.iter().all(Option::is_none));
+            assert!(step.final_states.iter().all(Option::is_none));
+        }
+        let step = idle(&mut vec_env, &layout);
+        for (env, &seed) in seeds.iter().enumerate() {
+            assert_eq!(step.end_reasons[env], Some(EndReason::MaxGameTicks));
+            let final_state = step.final_states[env].as_ref().unwrap();
+            assert_eq!(final_state.seed(), seed);
+            assert_eq!(final_state.ticks_progressed(), 3);
+            // observations are of the episode that just started
+            assert_eq!(vec_env.state(env).ticks_progressed(), 0);
+            assert!(vec_env.state(env).seed() != seed);
+        }
+        assert_eq!(step.observations, vec_env.flat_observations(&layout, false));
+    }
+
+    #[test]
+    fn batches_are_in_environment_order_whatever_the_threads() {
+        let layout = FlatLayout::default();
+        let config = MatchConfig { max_game_ticks: 10, ..MatchConfig::default() };
+        let mut single = VecEnv::new(config.clone(), 5, 9, Some(1));
+        let mut pooled = VecEnv::new(config, 5, 9, Some(3));
+        for _ in 0..12 {
+            let (one, many) = (idle(&mut single, &layout), idle(&mut pooled, &layout));
+            assert_eq!(many.rewards.len(), 5);
+            assert_eq!(many.observations.len(), 5 * 2 * layout.size());
+            assert_eq!(one.rewards, many.rewards);
+            assert_eq!(one.observations, many.observations);
+        }
+        let seeds = |vec_env: &VecEnv| -> Vec<u64> { vec_env.states().map(Gamestate::seed).collect() };
+        assert_eq!(seeds(&single), seeds(&pooled));
+    }
+}
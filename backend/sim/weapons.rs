@@ -0,0 +1,45 @@
+use crate::gamestate::{Player, Team};
+use crate::vec3::{distance, dot_vec3, length_vec3};
+
+// Homing for missiles: each one chases the closest living drone of another team, turning
+// a little every tick without changing speed, so fast drones can outturn it.
+
+/// The closest living drone of a team other than `team`
+pub(crate) fn closest_enemy<'a>(players: &'a [Player], team: Team, position: &[f32;3]) -> Option<&'a Player> {
+    players.iter()
+        .filter(|player| player.team != team && !player.is_dead)
+        .min_by(|p1, p2| distance(&p1.position, position).total_cmp(&distance(&p2.position, position)))
+}
+
+/// `velocity` turned towards `target` by at most `max_turn` radians, keeping its speed
+pub(crate) fn steer(velocity: &[f32;3], position: &[f32;3], target: &[f32;3], max_turn: f32) -> [f32;3] {
+    let speed = length_vec3(velocity);
+    let target_distance = distance(target, position);
+    if speed == 0.0 || target_distance == 0.0 {
+        return *velocity;
+    }
+    let heading = [velocity[0] / speed, velocity[1] / speed, velocity[2] / speed];
+    let wanted = [
+        (target[0] - position[0]) / target_distance,
+        (target[1] - position[1]) / target_distance,
+        (target[2] - position[2]) / target_distance,
+    ];
+    let cos_angle = dot_vec3(&heading, &wanted).clamp(-1.0, 1.0);
+    let angle = cos_angle.acos();
+    if angle <= max_turn {
+        return [wanted[0] * speed, wanted[1] * speed, wanted[2] * speed];
+    }
+    // straight behind there's no telling which way to turn, keep going
+    let sin_angle = angle.sin();
+    if sin_angle < 1e-6 {
+        return *velocity;
+    }
+    // rotate within the plane of the heading and the wanted direction
+    let keep = (angle - max_turn).sin() / sin_angle;
+    let turn = max_turn.sin() / sin_angle;
+    [
+        (heading[0] * keep + wanted[0] * turn) * speed,
+        (heading[1] * keep + wanted[1] * turn) * speed,
+        (heading[2] * keep + wanted[2] * turn) * speed,
+    ]
+}
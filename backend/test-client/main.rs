@@ -1,19 +1,41 @@
 use std::net::TcpStream;
-use std::io::{Read, Write};
+use std::io::{BufRead, BufReader, Write};
+use std::env;
+use serde::Deserialize;
+use drones_3d::{Controls, InputRaw};
+
+#[derive(Deserialize)]
+struct Handshake {
+    team: String,
+    team_size: usize,
+}
 
 fn main() {
-    let mut stream = TcpStream::connect("127.0.0.1:44556").expect("Could not connect to the server");
-    stream.write(b"COMPETITOR\n").expect("Failed to write to server");
+    // the tournament runner tells the bots it launches where their match is hosted
+    let server_addr = env::var("DRONES3D_SERVER_ADDR").unwrap_or(String::from("127.0.0.1:44556"));
+    let mut stream = TcpStream::connect(server_addr).expect("Could not connect to the server");
+    stream.write_all(b"COMPETITOR\n").expect("Failed to write to server");
+
+    let mut reader = BufReader::new(stream.try_clone().expect("Could not clone the stream"));
+    // the server first tells us our team and how many drones it has
+    let mut msg = String::new();
+    reader.read_line(&mut msg).expect("Failed to read from server");
+    let handshake: Handshake = serde_json::from_str(&msg).expect("Invalid handshake");
+    println!("Playing team {} with {} drones", handshake.team, handshake.team_size);
+
+    // all drones just fly forward
+    let input = InputRaw {
+        controls: vec![Controls { forward_back: 1.0, ..Controls::empty() }; handshake.team_size],
+    };
+    let response = format!("{}\n", serde_json::to_string(&input).unwrap());
 
-    let mut buffer = [0; 1024];
     loop {
-        let bytes_read = stream.read(&mut buffer).expect("Failed to read from server");
+        let mut msg = String::new();
+        let bytes_read = reader.read_line(&mut msg).expect("Failed to read from server");
         if bytes_read == 0 { return; } // Connection closed
 
-        let msg = String::from_utf8_lossy(&buffer[..bytes_read]);
         println!("Client got message: {}", msg);
 
-        let response = b"{rot_y:0,forward_back:1,up_down:0,shoot:false,shot_angle:0}{rot_y:0,forward_back:1,up_down:0,shoot:false,shot_angle:0}\n";
-        stream.write(response).expect("Failed to write to server");
+        stream.write_all(response.as_bytes()).expect("Failed to write to server");
     }
-}
\ No newline at end of file
+}
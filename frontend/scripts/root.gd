@@ -4,10 +4,8 @@ var send_identity = true
 var tcp_connection = StreamPeerTCP.new()
 var drone_a_tscn = preload("res://scenes/drone_a.tscn")
 var drone_b_tscn = preload("res://scenes/drone_b.tscn")
-var drone_a1 : Node3D
-var drone_a2 : Node3D
-var drone_b1 : Node3D
-var drone_b2 : Node3D
+# one per player in the gamestate, in the same order
+var drones : Array[Node3D] = []
 var obstacles_multimesh: MultiMesh
 var json = JSON.new()
 
@@ -17,14 +15,6 @@ func _ready():
 		print("Connecting to server...")
 	else: 
 		print("Couldn't connect to server")
-	drone_a1 = drone_a_tscn.instantiate()
-	add_child(drone_a1)
-	drone_a2 = drone_a_tscn.instantiate()
-	add_child(drone_a2)
-	drone_b1 = drone_b_tscn.instantiate()
-	add_child(drone_b1)
-	drone_b2 = drone_b_tscn.instantiate()
-	add_child(drone_b2)
 	obstacles_multimesh = $Obstacles.multimesh
 
 func _process(delta):
@@ -48,17 +38,22 @@ func update_gamestate(gamestate: String):
 			var scale = obstacle["radius"]
 			obstacles_multimesh.set_instance_transform(i,Transform3D.IDENTITY.translated(offset).scaled(Vector3.ONE * scale))
 			i += 1
-		drone_a1.position.x =  data_recieved["player_a1"]["position"][0]
-		drone_a1.position.y =  data_recieved["player_a1"]["position"][1]
-		drone_a1.position.z =  data_recieved["player_a1"]["position"][2]
-		drone_a2.position.x =  data_recieved["player_a2"]["position"][0]
-		drone_a2.position.y =  data_recieved["player_a2"]["position"][1]
-		drone_a2.position.z =  data_recieved["player_a2"]["position"][2]
-		drone_b1.position.x =  data_recieved["player_b1"]["position"][0]
-		drone_b1.position.y =  data_recieved["player_b1"]["position"][1]
-		drone_b1.position.z =  data_recieved["player_b1"]["position"][2]
-		drone_b2.position.x =  data_recieved["player_b2"]["position"][0]
-		drone_b2.position.y =  data_recieved["player_b2"]["position"][1]
-		drone_b2.position.z =  data_recieved["player_b2"]["position"][2]
+		var players = data_recieved["players"]
+		# the team size is only known once the first gamestate arrives
+		while drones.size() < players.size():
+			var tscn = drone_a_tscn if players[drones.size()]["team"] == "A" else drone_b_tscn
+			var drone = tscn.instantiate()
+			add_child(drone)
+			drones.append(drone)
+		for j in players.size():
+			update_drone(drones[j], players[j])
 	else:
 		print("Gamestate was not valid JSON: %s" % gamestate)
+
+func update_drone(drone: Node3D, player: Dictionary):
+	var pos = player["position"]
+	# yaw around z, then pitch the nose up around the drone's y, then roll around its x
+	var orientation = Basis(Vector3(0, 0, 1), player["rot_y"]) \
+		* Basis(Vector3(0, 1, 0), -player["pitch"]) \
+		* Basis(Vector3(1, 0, 0), player["roll"])
+	drone.transform = Transform3D(orientation, Vector3(pos[0], pos[1], pos[2]))